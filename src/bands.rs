@@ -0,0 +1,149 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use log::warn;
+
+/// IARU region, which decides the edges of the amateur bands.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum Region {
+    /// Europe, Africa, the Middle East and northern Asia
+    #[default]
+    One,
+    /// The Americas
+    Two,
+    /// Southern Asia and the Pacific
+    Three,
+}
+
+impl Region {
+    pub const ALL: [Region; 3] = [Region::One, Region::Two, Region::Three];
+
+    /// Short form for settings files.
+    pub fn key(self) -> String {
+        self.number().to_string()
+    }
+
+    fn number(self) -> u8 {
+        match self {
+            Region::One => 1,
+            Region::Two => 2,
+            Region::Three => 3,
+        }
+    }
+}
+
+impl Display for Region {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "IARU Region {}", self.number())
+    }
+}
+
+impl FromStr for Region {
+    type Err = String;
+
+    /// Parse the region number, as written by [`Region::key`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Region::ALL.into_iter()
+            .find(|region| region.number().to_string() == s.trim())
+            .ok_or(format!("unknown IARU region '{}'", s))
+    }
+}
+
+/// Named frequency range, either an amateur band or one set up by the user.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Band {
+    pub name: String,
+    /// Lower edge [Hz]
+    pub start_freq: i32,
+    /// Upper edge [Hz]
+    pub stop_freq: i32,
+}
+
+impl Band {
+    fn new(name: &str, start_freq: i32, stop_freq: i32) -> Self {
+        Self { name: name.to_string(), start_freq, stop_freq }
+    }
+}
+
+/// HF and 6 m amateur bands of `region`, from low to high.
+pub fn amateur_bands(region: Region) -> Vec<Band> {
+    let (top_160, top_80, top_40, top_6) = match region {
+        Region::One => (2_000_000, 3_800_000, 7_200_000, 52_000_000),
+        Region::Two => (2_000_000, 4_000_000, 7_300_000, 54_000_000),
+        Region::Three => (2_000_000, 3_900_000, 7_200_000, 54_000_000),
+    };
+    let bottom_160 = match region {
+        Region::One => 1_810_000,
+        Region::Two | Region::Three => 1_800_000,
+    };
+    vec![
+        Band::new("160m", bottom_160, top_160),
+        Band::new("80m", 3_500_000, top_80),
+        Band::new("60m", 5_351_500, 5_366_500),
+        Band::new("40m", 7_000_000, top_40),
+        Band::new("30m", 10_100_000, 10_150_000),
+        Band::new("20m", 14_000_000, 14_350_000),
+        Band::new("17m", 18_068_000, 18_168_000),
+        Band::new("15m", 21_000_000, 21_450_000),
+        Band::new("12m", 24_890_000, 24_990_000),
+        Band::new("10m", 28_000_000, 29_700_000),
+        Band::new("6m", 50_000_000, top_6),
+    ]
+}
+
+/// Ranges written by [`format_custom`], skipping any that can't be read.
+pub fn parse_custom(s: &str) -> Vec<Band> {
+    let parse = |band: &str| {
+        let mut fields = band.rsplitn(3, ':');
+        let stop_freq = fields.next()?.parse().ok()?;
+        let start_freq = fields.next()?.parse().ok()?;
+        let name = fields.next().filter(|name| !name.trim().is_empty())?;
+        Some(Band::new(name, start_freq, stop_freq))
+    };
+    s.split(',')
+        .filter(|band| !band.is_empty())
+        .filter_map(|band| {
+            let parsed = parse(band);
+            if parsed.is_none() {
+                warn!("ignoring custom band '{}' in the settings", band);
+            }
+            parsed
+        })
+        .collect()
+}
+
+/// Ranges as `name:start:stop`, separated by commas.
+pub fn format_custom(bands: &[Band]) -> String {
+    bands.iter()
+        .map(|band| format!("{}:{}:{}", band.name, band.start_freq, band.stop_freq))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_round_trip() {
+        let bands = vec![
+            Band::new("Club net", 3_700_000, 3_750_000),
+            Band::new("  spaced  out ", 7_050_000, 7_060_000),
+            Band::new("x", 100_000, 60_000_000),
+        ];
+        let parsed = parse_custom(&format_custom(&bands));
+        assert_eq!(parsed.len(), bands.len());
+        for (parsed, band) in parsed.iter().zip(&bands) {
+            assert_eq!((parsed.name.as_str(), parsed.start_freq, parsed.stop_freq),
+                       (band.name.as_str(), band.start_freq, band.stop_freq));
+        }
+    }
+
+    #[test]
+    fn custom_skips_malformed() {
+        let parsed = parse_custom("good:1000:2000,,no stop:1000,bad:x:2000,:3000:4000,alone");
+        let names: Vec<_> = parsed.iter().map(|band| band.name.as_str()).collect();
+        assert_eq!(names, ["good"]);
+        assert!(parse_custom("").is_empty());
+    }
+}
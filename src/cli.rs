@@ -0,0 +1,151 @@
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::ops::ControlFlow;
+use std::path::PathBuf;
+
+use clap::Args;
+
+use crate::frequency;
+use crate::protocol::{connect, Connection, error};
+use crate::protocol::dummy::AntennaModel;
+use crate::protocol::libusb::DeviceId;
+use crate::protocol::measurement::Detector;
+use crate::protocol::plan::{DEFAULT_NOISE_FILTER, SweepPlan};
+use crate::protocol::simulator::Faults;
+
+#[derive(Args)]
+pub struct SweepArgs {
+    /// Start frequency [Hz], or with a k, M or G suffix
+    #[arg(long, default_value = "1M", value_parser = frequency::parse)]
+    start: i32,
+    /// Stop frequency [Hz], or with a k, M or G suffix
+    #[arg(long, default_value = "35M", value_parser = frequency::parse)]
+    stop: i32,
+    /// Number of steps between start and stop
+    #[arg(long, default_value_t = 100)]
+    steps: i32,
+    /// Time per step [ms]
+    #[arg(long, default_value_t = 10)]
+    step_ms: i32,
+    /// Noise filter setting of the analyzer
+    #[arg(long, default_value_t = DEFAULT_NOISE_FILTER)]
+    noise_filter: i32,
+    /// Sweep the dummy device instead of a Fox-Delta
+    #[arg(long, conflicts_with = "serial")]
+    dummy: bool,
+    /// Sweep a simulated Fox-Delta speaking the real protocol
+    #[arg(long, conflicts_with_all = ["dummy", "serial"])]
+    simulator: bool,
+    /// Probability of a corrupted sample frame from the simulator
+    #[arg(long, default_value_t = 0.0, requires = "simulator")]
+    bad_frames: f64,
+    /// Probability of a lost reply from the simulator
+    #[arg(long, default_value_t = 0.0, requires = "simulator")]
+    timeouts: f64,
+    /// Probability of the simulator refusing the exit command, making it retry
+    #[arg(long, default_value_t = 0.0, requires = "simulator")]
+    nak: f64,
+    /// Serial number or bus:address of the Fox-Delta to use when several are attached
+    #[arg(long, conflicts_with_all = ["dummy", "serial", "simulator"])]
+    device: Option<DeviceId>,
+    /// Serial port of a Fox-Delta compatible analyzer, instead of USB
+    #[arg(long)]
+    serial: Option<PathBuf>,
+    /// Baud rate of the serial port
+    #[arg(long, default_value_t = 115200, requires = "serial")]
+    baud: u32,
+    /// Write the samples to a file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+/// Run a single sweep without the UI, writing one tab separated line per sample.
+pub fn sweep(args: SweepArgs) -> error::Result<()> {
+    let connection = match args.serial {
+        Some(path) => Connection::Serial { path, baud: args.baud },
+        None if args.dummy => Connection::Dummy(AntennaModel::default()),
+        None if args.simulator => Connection::Simulator(Faults {
+            bad_frame: args.bad_frames,
+            timeout: args.timeouts,
+            nak: args.nak,
+        }),
+        None => Connection::FoxDelta(args.device),
+    };
+    let plan = SweepPlan::new(args.start, args.stop, args.steps, args.step_ms, args.noise_filter);
+    plan.check()?;
+    if plan.step_count() != args.steps {
+        eprintln!("sweeping {} steps, the nearest count that ends on the stop frequency", plan.step_count());
+    }
+
+    let (mut device, version) = connect(&connection)?;
+    eprintln!("connected to {}", version);
+
+    // Only now that the sweep can run is an existing output file replaced
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout().lock()),
+    };
+
+    let detector = Detector::default();
+    writeln!(out, "index\tfrequency\tvalue\treflection\tswr\treturn_loss")?;
+    let mut result = Ok(());
+    plan.run(device.as_mut(), false, &mut |i, freq, value| {
+        let reflection = detector.reflection(value as f32);
+        result = writeln!(out, "{i}\t{freq}\t{value}\t{:.4}\t{:.3}\t{:.2}",
+                          reflection.magnitude(),
+                          reflection.swr(),
+                          reflection.return_loss());
+        if result.is_err() {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    })?;
+    result?;
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: SweepArgs,
+    }
+
+    /// Run a sweep into an existing file, returning what is left of it.
+    fn sweep_into(name: &str, args: &[&str]) -> String {
+        let path = std::env::temp_dir().join(format!("swr-analyzer-test-{}-{name}.tsv", std::process::id()));
+        std::fs::write(&path, "earlier sweep\n").unwrap();
+        let output = path.to_string_lossy().into_owned();
+        let cli = Cli::parse_from(["sweep", "--output", &output].iter().chain(args));
+        let result = sweep(cli.args);
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(result.is_ok(), contents.starts_with("index"), "{:?}", result.err());
+        contents
+    }
+
+    #[test]
+    fn failed_connect_keeps_output() {
+        assert_eq!(sweep_into("connect", &["--serial", "/nonexistent/tty"]), "earlier sweep\n");
+    }
+
+    #[test]
+    fn invalid_sweep_keeps_output() {
+        assert_eq!(sweep_into("invalid", &["--dummy", "--start", "1", "--stop", "10"]), "earlier sweep\n");
+    }
+
+    #[test]
+    fn sweep_replaces_output() {
+        let contents = sweep_into("replace", &["--dummy", "--steps", "10", "--step-ms", "0"]);
+        assert!(contents.starts_with("index\tfrequency"));
+        assert_eq!(contents.lines().count(), 12);
+    }
+}
@@ -0,0 +1,198 @@
+use std::borrow::Cow;
+use std::io;
+use std::io::{BufRead, Write};
+
+use chrono::{DateTime, Local};
+
+use crate::format::error::{Error, Result};
+use crate::format::{format_params, measured, parse_params, Trace};
+use crate::protocol::measurement::Reflection;
+
+const HEADER: &str = "# swr-analyzer traces";
+const METADATA_COLUMNS: &str = "# trace,name,color,device,timestamp,noise_filter,start_freq,step_freq,step_count,step_millis";
+const DATA_COLUMNS: &str = "trace,frequency_hz,reflection,swr,return_loss_db";
+
+/// Write `traces` as CSV, with one commented metadata line per trace followed by all samples.
+///
+/// The SWR and return loss columns are for convenience in spreadsheets and are ignored on import.
+/// Points a sweep didn't reach are left out.
+pub fn write(mut w: impl Write, traces: &[Trace]) -> io::Result<()> {
+    writeln!(w, "{}", HEADER)?;
+    writeln!(w, "{}", METADATA_COLUMNS)?;
+    for (i, trace) in traces.iter().enumerate() {
+        let params = trace.params.as_ref().map_or_else(|| ",,,,".to_string(), format_params);
+        writeln!(w, "# {},{},{},{},{},{}",
+                 i + 1,
+                 quote(&trace.name),
+                 trace.color.as_deref().unwrap_or_default(),
+                 quote(trace.device.as_deref().unwrap_or_default()),
+                 trace.timestamp.map(|t| t.to_rfc3339()).unwrap_or_default(),
+                 params)?;
+    }
+    writeln!(w, "{}", DATA_COLUMNS)?;
+    for (i, trace) in traces.iter().enumerate() {
+        for (freq, reflection) in measured(&trace.samples) {
+            let r = Reflection::new(reflection);
+            writeln!(w, "{},{},{},{},{}", i + 1, freq, reflection, r.swr(), r.return_loss())?;
+        }
+    }
+    w.flush()
+}
+
+/// Read traces written by [`write`], in the order they appear in the file.
+pub fn read(r: impl BufRead) -> Result<Vec<Trace>> {
+    let mut traces: Vec<(String, Trace)> = vec![];
+    let mut lines = r.lines().enumerate();
+    while let Some((i, line)) = lines.next() {
+        let mut line = line?;
+        let parse_error = |msg: String| Error::Parse { line: i + 1, msg };
+        // A quoted field can hold line breaks, the record goes on until its quotes are balanced
+        while line.matches('"').count() % 2 == 1 {
+            let Some((_, next)) = lines.next() else {
+                return Err(parse_error("unterminated quoted field".to_string()));
+            };
+            line.push('\n');
+            line.push_str(&next?);
+        }
+        if line.trim().is_empty() || line == HEADER || line == METADATA_COLUMNS || line == DATA_COLUMNS {
+            continue;
+        }
+
+        if let Some(metadata) = line.strip_prefix("# ") {
+            let fields = split(metadata);
+            let [id, name, color, device, timestamp, params @ ..] = fields.as_slice() else {
+                return Err(parse_error("incomplete trace metadata".to_string()));
+            };
+            let timestamp = if timestamp.is_empty() {
+                None
+            } else {
+                let timestamp = DateTime::parse_from_rfc3339(timestamp)
+                    .map_err(|e| parse_error(format!("timestamp: {e}")))?;
+                Some(timestamp.with_timezone(&Local))
+            };
+            let params = if params.iter().all(|f| f.is_empty()) {
+                None
+            } else {
+                Some(parse_params(params.iter().map(String::as_str)).map_err(parse_error)?)
+            };
+            let trace = Trace {
+                name: name.clone(),
+                color: Some(color.clone()).filter(|c| !c.is_empty()),
+                device: Some(device.clone()).filter(|d| !d.is_empty()),
+                timestamp,
+                params,
+                samples: vec![],
+            };
+            traces.push((id.clone(), trace));
+            continue;
+        }
+
+        let fields = split(&line);
+        let [id, freq, reflection, ..] = fields.as_slice() else {
+            return Err(parse_error("expected trace, frequency and reflection".to_string()));
+        };
+        let freq: f32 = freq.parse().map_err(|e| parse_error(format!("frequency: {e}")))?;
+        let reflection: f32 = reflection.parse().map_err(|e| parse_error(format!("reflection: {e}")))?;
+
+        let index = match traces.iter().position(|(trace_id, _)| trace_id == id) {
+            Some(index) => index,
+            None => {
+                traces.push((id.clone(), Trace { name: format!("Trace {}", id), ..Default::default() }));
+                traces.len() - 1
+            }
+        };
+        traces[index].1.samples.push((freq, reflection));
+    }
+    Ok(traces.into_iter().map(|(_, trace)| trace).collect())
+}
+
+fn quote(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+/// Split a CSV line into fields, handling quoted fields with escaped quotes.
+fn split(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::SweepParams;
+
+    #[test]
+    fn round_trip() {
+        let traces = [
+            Trace {
+                name: "dipole, \"inverted\" V".to_string(),
+                color: Some("#ff0000".to_string()),
+                device: Some("FoxDelta\nfirmware 1.2".to_string()),
+                timestamp: Some(DateTime::parse_from_rfc3339("2026-10-17T12:00:00+02:00").unwrap().with_timezone(&Local)),
+                params: Some(SweepParams {
+                    noise_filter: 600,
+                    start_freq: 7_000_000,
+                    step_freq: 100_000,
+                    step_count: 2,
+                    step_millis: 10,
+                }),
+                samples: vec![(7_100_000.0, 0.25), (0.0, 0.0), (7_000_000.0, 0.5)],
+            },
+            Trace {
+                name: "two\nlines\n\"quoted\"".to_string(),
+                samples: vec![(1_000_000.0, 0.125)],
+                ..Default::default()
+            },
+        ];
+        let mut file = vec![];
+        write(&mut file, &traces).unwrap();
+        let read = read(file.as_slice()).unwrap();
+
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[0].name, traces[0].name);
+        assert_eq!(read[0].color, traces[0].color);
+        assert_eq!(read[0].device, traces[0].device);
+        assert_eq!(read[0].timestamp, traces[0].timestamp);
+        let params = read[0].params.unwrap();
+        assert_eq!((params.start_freq, params.step_freq, params.step_count), (7_000_000, 100_000, 2));
+        // Unmeasured points are left out and the rest sorted by frequency
+        assert_eq!(read[0].samples, [(7_000_000.0, 0.5), (7_100_000.0, 0.25)]);
+
+        assert_eq!(read[1].name, traces[1].name);
+        assert_eq!(read[1].device, None);
+        assert!(read[1].params.is_none());
+        assert_eq!(read[1].samples, [(1_000_000.0, 0.125)]);
+    }
+
+    #[test]
+    fn split_quoted_fields() {
+        assert_eq!(split("1,\"a,b\",\"say \"\"hi\"\"\",,x"), ["1", "a,b", "say \"hi\"", "", "x"]);
+    }
+
+    #[test]
+    fn rejects_bad_lines() {
+        assert!(read("# 1,\"unterminated\n".as_bytes()).is_err());
+        assert!(read("# 1,name\n".as_bytes()).is_err());
+        assert!(read("1,7000000\n".as_bytes()).is_err());
+        assert!(read("1,7M,0.5\n".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,14 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("i/o error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("line {line}: {msg}")]
+    Parse {
+        line: usize,
+        msg: String,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,45 @@
+use chrono::{DateTime, Local};
+
+use crate::protocol::SweepParams;
+
+pub mod csv;
+pub mod error;
+pub mod session;
+pub mod touchstone;
+
+/// A trace together with the details of how it was measured.
+#[derive(Clone, Debug, Default)]
+pub struct Trace {
+    pub name: String,
+    /// Color as `#rrggbb`
+    pub color: Option<String>,
+    pub device: Option<String>,
+    pub timestamp: Option<DateTime<Local>>,
+    pub params: Option<SweepParams>,
+    /// (frequency [Hz], |Γ|)
+    pub samples: Vec<(f32, f32)>,
+}
+
+/// Samples that were measured, sorted by frequency, leaving out placeholders of points a sweep
+/// didn't reach.
+fn measured(samples: &[(f32, f32)]) -> Vec<(f32, f32)> {
+    let mut samples: Vec<_> = samples.iter().copied().filter(|&(freq, _)| freq > 0.0).collect();
+    samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+    samples
+}
+
+/// Sweep parameters as the comma separated fields used in csv and session files.
+fn format_params(p: &SweepParams) -> String {
+    format!("{},{},{},{},{}", p.noise_filter, p.start_freq, p.step_freq, p.step_count, p.step_millis)
+}
+
+fn parse_params<'a>(fields: impl IntoIterator<Item=&'a str>) -> Result<SweepParams, String> {
+    let values: Vec<i32> = fields.into_iter()
+        .map(|f| f.trim().parse())
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("sweep parameters: {e}"))?;
+    let &[noise_filter, start_freq, step_freq, step_count, step_millis] = values.as_slice() else {
+        return Err("expected 5 sweep parameters".to_string());
+    };
+    Ok(SweepParams { noise_filter, start_freq, step_freq, step_count, step_millis })
+}
@@ -0,0 +1,276 @@
+use std::borrow::Cow;
+use std::io;
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+
+use chrono::{DateTime, Local};
+
+use crate::format::error::{Error, Result};
+use crate::format::{format_params, parse_params, Trace};
+
+const HEADER: &str = "# swr-analyzer session";
+
+/// Everything needed to reopen the workspace as it was.
+#[derive(Clone, Debug, Default)]
+pub struct Session {
+    /// Text of the sweep parameter entries, by name
+    pub controls: Vec<(String, String)>,
+    pub view: Extents,
+    pub traces: Vec<SessionTrace>,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Extents {
+    pub x_min: f32,
+    pub x_max: f32,
+    pub y_min: f32,
+    pub y_max: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct SessionTrace {
+    pub trace: Trace,
+    pub visible: bool,
+    pub extents: Extents,
+}
+
+#[derive(Copy, Clone)]
+enum Section {
+    None,
+    Controls,
+    View,
+    Trace,
+}
+
+/// Write the session as an INI style text file, with the samples of each trace listed in its section.
+pub fn write(mut w: impl Write, session: &Session) -> io::Result<()> {
+    writeln!(w, "{}", HEADER)?;
+    writeln!(w, "[controls]")?;
+    for (name, value) in &session.controls {
+        writeln!(w, "{}={}", escape(name), escape(value))?;
+    }
+    writeln!(w, "[view]")?;
+    write_extents(&mut w, &session.view)?;
+    for SessionTrace { trace, visible, extents } in &session.traces {
+        writeln!(w, "[trace]")?;
+        writeln!(w, "name={}", escape(&trace.name))?;
+        if let Some(color) = &trace.color {
+            writeln!(w, "color={}", escape(color))?;
+        }
+        if let Some(device) = &trace.device {
+            writeln!(w, "device={}", escape(device))?;
+        }
+        if let Some(timestamp) = &trace.timestamp {
+            writeln!(w, "timestamp={}", timestamp.to_rfc3339())?;
+        }
+        if let Some(p) = &trace.params {
+            writeln!(w, "params={}", format_params(p))?;
+        }
+        writeln!(w, "visible={}", visible)?;
+        write_extents(&mut w, extents)?;
+        for (freq, reflection) in &trace.samples {
+            writeln!(w, "{} {}", freq, reflection)?;
+        }
+    }
+    w.flush()
+}
+
+pub fn read(r: impl BufRead) -> Result<Session> {
+    let mut session = Session::default();
+    let mut section = Section::None;
+    for (i, line) in r.lines().enumerate() {
+        let line = line?;
+        let parse_error = |msg: String| Error::Parse { line: i + 1, msg };
+        if i == 0 && line != HEADER {
+            return Err(parse_error("not a session file".to_string()));
+        }
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        match line.as_str() {
+            "[controls]" => section = Section::Controls,
+            "[view]" => section = Section::View,
+            "[trace]" => {
+                section = Section::Trace;
+                session.traces.push(SessionTrace {
+                    trace: Trace::default(),
+                    visible: true,
+                    extents: Extents::default(),
+                });
+            }
+            _ => {
+                match (section, split_entry(&line), session.traces.last_mut()) {
+                    (Section::Controls, Some((name, value)), _) => {
+                        session.controls.push((unescape(name), unescape(value)));
+                    }
+                    (Section::View, Some((name, value)), _) => {
+                        read_extent(&mut session.view, name, value).map_err(parse_error)?;
+                    }
+                    (Section::Trace, Some((name, value)), Some(t)) => {
+                        match name {
+                            "name" => t.trace.name = unescape(value),
+                            "color" => t.trace.color = Some(unescape(value)),
+                            "device" => t.trace.device = Some(unescape(value)),
+                            "timestamp" => {
+                                let timestamp = DateTime::parse_from_rfc3339(value)
+                                    .map_err(|e| parse_error(format!("timestamp: {e}")))?;
+                                t.trace.timestamp = Some(timestamp.with_timezone(&Local));
+                            }
+                            "params" => t.trace.params = Some(parse_params(value.split(',')).map_err(parse_error)?),
+                            "visible" => t.visible = parse(name, value).map_err(parse_error)?,
+                            _ => read_extent(&mut t.extents, name, value).map_err(parse_error)?,
+                        }
+                    }
+                    (Section::Trace, None, Some(t)) => {
+                        let Some((freq, reflection)) = line.split_once(' ') else {
+                            return Err(parse_error("expected frequency and reflection".to_string()));
+                        };
+                        t.trace.samples.push((
+                            parse("frequency", freq).map_err(parse_error)?,
+                            parse("reflection", reflection).map_err(parse_error)?,
+                        ));
+                    }
+                    _ => return Err(parse_error(format!("unexpected line '{line}'"))),
+                }
+            }
+        }
+    }
+    Ok(session)
+}
+
+/// Escape the characters that would end an entry or split it into key and value early.
+fn escape(text: &str) -> Cow<'_, str> {
+    if !text.contains(['\\', '\n', '\r', '=']) {
+        return Cow::Borrowed(text);
+    }
+    let mut escaped = String::with_capacity(text.len() + 2);
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '=' => escaped.push_str("\\="),
+            c => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+/// Undo [`escape`], keeping a backslash before anything it doesn't escape.
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some(next @ ('\\' | 'n' | 'r' | '='))) => {
+                unescaped.push(match next {
+                    'n' => '\n',
+                    'r' => '\r',
+                    next => next,
+                });
+                chars.next();
+            }
+            (c, _) => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+/// Split an entry into key and value at the first `=` that isn't escaped.
+fn split_entry(line: &str) -> Option<(&str, &str)> {
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '=' if !escaped => return Some((&line[..i], &line[i + 1..])),
+            '\\' => escaped = !escaped,
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+fn write_extents(w: &mut impl Write, extents: &Extents) -> io::Result<()> {
+    writeln!(w, "x_min={}", extents.x_min)?;
+    writeln!(w, "x_max={}", extents.x_max)?;
+    writeln!(w, "y_min={}", extents.y_min)?;
+    writeln!(w, "y_max={}", extents.y_max)
+}
+
+fn read_extent(extents: &mut Extents, name: &str, value: &str) -> std::result::Result<(), String> {
+    let extent = match name {
+        "x_min" => &mut extents.x_min,
+        "x_max" => &mut extents.x_max,
+        "y_min" => &mut extents.y_min,
+        "y_max" => &mut extents.y_max,
+        _ => return Err(format!("unknown key '{name}'")),
+    };
+    *extent = parse(name, value)?;
+    Ok(())
+}
+
+fn parse<T: FromStr>(name: &str, value: &str) -> std::result::Result<T, String>
+    where T::Err: std::fmt::Display {
+    value.trim().parse().map_err(|e| format!("{name}: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(session: &Session) -> Session {
+        let mut file = vec![];
+        write(&mut file, session).unwrap();
+        read(file.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn names_round_trip() {
+        let names = ["plain", "a=b", "two\nlines", "cr\r\n", "back\\slash", "\\n=\\", "ends with \\"];
+        let session = Session {
+            controls: vec![("odd=name\n".to_string(), "x=1\\".to_string())],
+            traces: names.iter().map(|name| SessionTrace {
+                trace: Trace {
+                    name: name.to_string(),
+                    device: Some(format!("device {}", name)),
+                    samples: vec![(1000.0, 0.5)],
+                    ..Default::default()
+                },
+                visible: true,
+                extents: Extents::default(),
+            }).collect(),
+            ..Default::default()
+        };
+
+        let read = round_trip(&session);
+        assert_eq!(read.controls, session.controls);
+        assert_eq!(read.traces.len(), names.len());
+        for (t, name) in read.traces.iter().zip(names) {
+            assert_eq!(t.trace.name, name);
+            assert_eq!(t.trace.device.as_deref(), Some(format!("device {}", name).as_str()));
+            assert_eq!(t.trace.samples, [(1000.0, 0.5)]);
+        }
+    }
+
+    #[test]
+    fn escaped_entries_stay_on_one_line() {
+        let mut file = vec![];
+        let session = Session {
+            traces: vec![SessionTrace {
+                trace: Trace { name: "a\nb=c".to_string(), ..Default::default() },
+                visible: true,
+                extents: Extents::default(),
+            }],
+            ..Default::default()
+        };
+        write(&mut file, &session).unwrap();
+        assert!(String::from_utf8(file).unwrap().lines().any(|line| line == "name=a\\nb\\=c"));
+    }
+
+    #[test]
+    fn unknown_escapes_are_kept() {
+        assert_eq!(unescape("C:\\dir\\"), "C:\\dir\\");
+        assert_eq!(split_entry("a\\=b=c"), Some(("a\\=b", "c")));
+        assert_eq!(split_entry("a\\\\=b"), Some(("a\\\\", "b")));
+    }
+}
@@ -0,0 +1,201 @@
+use std::io;
+use std::io::{BufRead, Write};
+use std::num::ParseFloatError;
+
+use crate::format::error::{Error, Result};
+use crate::format::measured;
+
+/// Reference impedance written to exported files [Ω].
+const REFERENCE_IMPEDANCE: f32 = 50.0;
+
+/// Write `samples` of (frequency [Hz], |Γ|) as a one-port Touchstone file.
+///
+/// The detector only measures magnitude, so every angle is written as 0. Points a sweep didn't
+/// reach are left out.
+pub fn write(mut w: impl Write, name: &str, samples: &[(f32, f32)]) -> io::Result<()> {
+    writeln!(w, "! {}", name)?;
+    writeln!(w, "! Exported by swr-analyzer, scalar measurement without phase")?;
+    writeln!(w, "# HZ S MA R {}", REFERENCE_IMPEDANCE)?;
+    for (freq, reflection) in measured(samples) {
+        writeln!(w, "{} {} 0", freq, reflection)?;
+    }
+    w.flush()
+}
+
+/// Read a one-port Touchstone file as (frequency [Hz], |Γ|) samples.
+pub fn read(r: impl BufRead) -> Result<Vec<(f32, f32)>> {
+    let mut options: Option<Options> = None;
+    let mut samples = vec![];
+    for (i, line) in r.lines().enumerate() {
+        let line = line?;
+        let line = line.split('!').next().unwrap_or_default().trim();
+        // Skip empty lines and Touchstone 2.0 keywords, the data layout of one-port files is the same
+        if line.is_empty() || line.starts_with('[') {
+            continue;
+        }
+        if let Some(option_line) = line.strip_prefix('#') {
+            // Only the first option line counts
+            if options.is_none() {
+                options = Some(Options::parse(option_line).map_err(|msg| Error::Parse { line: i + 1, msg })?);
+            }
+            continue;
+        }
+        let options = options.get_or_insert_with(Options::default);
+
+        let fields: Vec<f32> = line.split_whitespace()
+            .map(str::parse)
+            .collect::<std::result::Result<_, ParseFloatError>>()
+            .map_err(|e| Error::Parse { line: i + 1, msg: e.to_string() })?;
+        let &[freq, a, b] = fields.as_slice() else {
+            return Err(Error::Parse {
+                line: i + 1,
+                msg: "expected a frequency and a single parameter, only one-port files are supported".to_string(),
+            });
+        };
+        samples.push((freq * options.unit, options.reflection(a, b)));
+    }
+    Ok(samples)
+}
+
+#[derive(Copy, Clone)]
+enum Parameter {
+    S,
+    Z,
+    Y,
+}
+
+#[derive(Copy, Clone)]
+enum Format {
+    MagnitudeAngle,
+    DecibelAngle,
+    RealImaginary,
+}
+
+struct Options {
+    /// Multiplier from the file frequency unit to Hz
+    unit: f32,
+    parameter: Parameter,
+    format: Format,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            unit: 1e9,
+            parameter: Parameter::S,
+            format: Format::MagnitudeAngle,
+        }
+    }
+}
+
+impl Options {
+    fn parse(line: &str) -> std::result::Result<Self, String> {
+        let mut this = Self::default();
+        let mut tokens = line.split_whitespace();
+        while let Some(token) = tokens.next() {
+            match token.to_ascii_uppercase().as_str() {
+                "HZ" => this.unit = 1.0,
+                "KHZ" => this.unit = 1e3,
+                "MHZ" => this.unit = 1e6,
+                "GHZ" => this.unit = 1e9,
+                "S" => this.parameter = Parameter::S,
+                "Z" => this.parameter = Parameter::Z,
+                "Y" => this.parameter = Parameter::Y,
+                "MA" => this.format = Format::MagnitudeAngle,
+                "DB" => this.format = Format::DecibelAngle,
+                "RI" => this.format = Format::RealImaginary,
+                "R" => {
+                    // Z and Y parameters are normalised to the reference, so its value is not needed
+                    tokens.next().ok_or("missing reference impedance")?;
+                }
+                other => return Err(format!("unsupported option '{}'", other)),
+            }
+        }
+        Ok(this)
+    }
+
+    /// |Γ| for a data point, converting Z and Y parameters to reflection.
+    fn reflection(&self, a: f32, b: f32) -> f32 {
+        let (re, im) = match self.format {
+            Format::MagnitudeAngle => (a * b.to_radians().cos(), a * b.to_radians().sin()),
+            Format::DecibelAngle => {
+                let magnitude = 10f32.powf(a / 20.0);
+                (magnitude * b.to_radians().cos(), magnitude * b.to_radians().sin())
+            }
+            Format::RealImaginary => (a, b),
+        };
+        match self.parameter {
+            Parameter::S => re.hypot(im),
+            // Γ = (z - 1) / (z + 1)
+            Parameter::Z => (re - 1.0).hypot(im) / (re + 1.0).hypot(im),
+            // Γ = (1 - y) / (1 + y)
+            Parameter::Y => (1.0 - re).hypot(im) / (1.0 + re).hypot(im),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_str(file: &str) -> Vec<(f32, f32)> {
+        read(file.as_bytes()).unwrap()
+    }
+
+    fn assert_close(samples: &[(f32, f32)], expected: &[(f32, f32)]) {
+        assert_eq!(samples.len(), expected.len());
+        for (&(freq, reflection), &(expected_freq, expected_reflection)) in samples.iter().zip(expected) {
+            assert!((freq - expected_freq).abs() <= expected_freq * 1e-6, "{} Hz, expected {}", freq, expected_freq);
+            assert!((reflection - expected_reflection).abs() < 1e-4, "|Γ| {}, expected {}", reflection, expected_reflection);
+        }
+    }
+
+    #[test]
+    fn frequency_units() {
+        assert_close(&read_str("# HZ\n1000 0.5 0\n"), &[(1e3, 0.5)]);
+        assert_close(&read_str("# KHZ\n1000 0.5 0\n"), &[(1e6, 0.5)]);
+        assert_close(&read_str("# mhz\n7.1 0.5 0\n"), &[(7.1e6, 0.5)]);
+        assert_close(&read_str("# GHZ\n0.01 0.5 0\n"), &[(1e7, 0.5)]);
+    }
+
+    #[test]
+    fn data_formats() {
+        assert_close(&read_str("# HZ S MA\n1 0.5 90\n"), &[(1.0, 0.5)]);
+        assert_close(&read_str("# HZ S DB\n1 -6.0206 45\n"), &[(1.0, 0.5)]);
+        assert_close(&read_str("# HZ S RI\n1 0.3 -0.4\n"), &[(1.0, 0.5)]);
+    }
+
+    #[test]
+    fn impedance_and_admittance() {
+        // z = 3 normalised gives Γ = 0.5, as does y = 1/3
+        assert_close(&read_str("# HZ Z RI R 50\n1 3 0\n"), &[(1.0, 0.5)]);
+        assert_close(&read_str("# HZ Y RI R 50\n1 0.333333 0\n"), &[(1.0, 0.5)]);
+        // A matched load reflects nothing, whatever it is given as
+        assert_close(&read_str("# HZ Z RI R 50\n1 1 0\n"), &[(1.0, 0.0)]);
+        assert_close(&read_str("# HZ Y MA R 50\n1 1 0\n"), &[(1.0, 0.0)]);
+    }
+
+    #[test]
+    fn default_options() {
+        // Without an option line the data is GHZ S MA R 50
+        assert_close(&read_str("! no options\n0.007 0.25 30\n"), &[(7e6, 0.25)]);
+        assert_close(&read_str("#\n0.007 0.25 30\n"), &[(7e6, 0.25)]);
+    }
+
+    #[test]
+    fn rejects_bad_files() {
+        assert!(read("# HZ S XX\n".as_bytes()).is_err());
+        assert!(read("# HZ S MA R\n".as_bytes()).is_err());
+        assert!(read("# HZ\n1 0.5 0 0.1 0\n".as_bytes()).is_err());
+        assert!(read("# HZ\n1 half 0\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn round_trip_skips_unmeasured() {
+        let samples = [(7_000_000.0, 0.5), (0.0, 0.0), (7_100_000.0, 0.25), (6_900_000.0, 0.75), (0.0, 0.0)];
+        let mut file = vec![];
+        write(&mut file, "dipole", &samples).unwrap();
+        assert_close(&read(file.as_slice()).unwrap(),
+                     &[(6_900_000.0, 0.75), (7_000_000.0, 0.5), (7_100_000.0, 0.25)]);
+    }
+}
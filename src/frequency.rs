@@ -0,0 +1,109 @@
+/// Parse a frequency in Hz, optionally with a unit suffix: `7100000`, `7100k`, `7.1M`, `7.1 MHz`.
+///
+/// A lower case `m` is read as mega too, as milli-hertz make no sense here.
+pub fn parse(s: &str) -> Result<i32, String> {
+    let s = s.trim();
+    let number_end = s.find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(number_end);
+    let multiplier = match unit.trim().to_ascii_lowercase().trim_end_matches("hz") {
+        "" => 1e0,
+        "k" => 1e3,
+        "m" => 1e6,
+        "g" => 1e9,
+        _ => return Err(format!("unknown unit in '{}', use Hz, k, M or G", s)),
+    };
+    let value = number.parse::<f64>()
+        .map_err(|_| format!("'{}' is not a frequency", s))?
+        * multiplier;
+    if !value.is_finite() || value.round() < i32::MIN as f64 || value.round() > i32::MAX as f64 {
+        return Err(format!("'{}' is out of range", s));
+    }
+    Ok(value.round() as i32)
+}
+
+/// Centre and span of the range from `start` to `stop`, the centre rounded down to whole hertz.
+pub fn to_centre_span(start: i32, stop: i32) -> (i32, i32) {
+    let span = stop - start;
+    (start + span / 2, span)
+}
+
+/// Start and stop of a range given by centre and span, undoing [`to_centre_span`] exactly.
+pub fn from_centre_span(centre: i32, span: i32) -> (i32, i32) {
+    let start = centre.saturating_sub(span / 2);
+    (start, start.saturating_add(span))
+}
+
+/// Shortest form of `freq` that [`parse`] reads back exactly, e.g. `14.2M` or `950k`.
+pub fn format(freq: i32) -> String {
+    match freq.unsigned_abs() {
+        f if f >= 1_000_000 => format!("{}M", freq as f64 / 1e6),
+        f if f >= 1_000 => format!("{}k", freq as f64 / 1e3),
+        _ => freq.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_units() {
+        assert_eq!(parse("7100000"), Ok(7_100_000));
+        assert_eq!(parse("7100k"), Ok(7_100_000));
+        assert_eq!(parse("7.1M"), Ok(7_100_000));
+        assert_eq!(parse("7.1 MHz"), Ok(7_100_000));
+        assert_eq!(parse(" 7.1mhz "), Ok(7_100_000));
+        assert_eq!(parse("0.06G"), Ok(60_000_000));
+        assert_eq!(parse("500 Hz"), Ok(500));
+    }
+
+    #[test]
+    fn lower_case_m_is_mega() {
+        assert_eq!(parse("14m"), Ok(14_000_000));
+    }
+
+    #[test]
+    fn signs() {
+        assert_eq!(parse("+1k"), Ok(1_000));
+        assert_eq!(parse("-1.5k"), Ok(-1_500));
+    }
+
+    #[test]
+    fn rounds_to_whole_hertz() {
+        assert_eq!(parse("1.0000004M"), Ok(1_000_000));
+        assert_eq!(parse("1.0000006M"), Ok(1_000_001));
+    }
+
+    #[test]
+    fn rejects() {
+        assert!(parse("").is_err());
+        assert!(parse("M").is_err());
+        assert!(parse("7.1.2M").is_err());
+        assert!(parse("7.1 MHzz").is_err());
+        assert!(parse("7.1x").is_err());
+        assert!(parse("3G").is_err());
+        assert!(parse("-3G").is_err());
+        assert!(parse("1e400").is_err());
+    }
+
+    #[test]
+    fn format_round_trip() {
+        for freq in [0, 1, 999, 1_000, 950_000, 1_000_000, 14_200_000, 14_200_001, 7_123_456, -1_500, i32::MAX, i32::MIN] {
+            assert_eq!(parse(&format(freq)), Ok(freq), "{}", format(freq));
+        }
+        assert_eq!(format(14_200_000), "14.2M");
+        assert_eq!(format(950_000), "950k");
+    }
+
+    #[test]
+    fn centre_span_round_trip() {
+        for (start, stop) in [(7_000_000, 7_200_000), (7_000_000, 7_200_001), (1, 2), (100_000, 60_000_000)] {
+            let (centre, span) = to_centre_span(start, stop);
+            assert_eq!(from_centre_span(centre, span), (start, stop));
+            // Through the entry text, as switching modes does
+            let (centre, span) = (parse(&format(centre)).unwrap(), parse(&format(span)).unwrap());
+            assert_eq!(from_centre_span(centre, span), (start, stop));
+        }
+    }
+}
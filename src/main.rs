@@ -2,12 +2,16 @@ use std::fs::File;
 use std::io;
 use std::io::{ErrorKind, Write};
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use log::error;
 use relm4::RelmApp;
 
 use ui::App;
 
+mod bands;
+mod cli;
+mod format;
+mod frequency;
 mod protocol;
 mod ui;
 
@@ -18,6 +22,14 @@ struct Args {
     udev: bool,
     #[arg(long)]
     no_elevate: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run a single sweep and print the samples without opening a window
+    Sweep(cli::SweepArgs),
 }
 
 fn main() {
@@ -30,6 +42,14 @@ fn main() {
         return;
     }
 
+    if let Some(Command::Sweep(sweep)) = args.command {
+        if let Err(e) = cli::sweep(sweep) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let app = RelmApp::new("nl.vbaarle.ruben.swranalyzer");
     app.run::<App>(());
 }
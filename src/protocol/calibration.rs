@@ -0,0 +1,231 @@
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::protocol::error::{Error, Result};
+use crate::protocol::measurement::Detector;
+
+const HEADER: &str = "# swr-analyzer calibration";
+const EXTENSION: &str = "cal";
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Standard {
+    Open,
+    Short,
+    Load,
+}
+
+impl Standard {
+    /// The standards in the order they are measured.
+    pub const ALL: [Standard; 3] = [Standard::Open, Standard::Short, Standard::Load];
+}
+
+impl Display for Standard {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Standard::Open => write!(f, "open"),
+            Standard::Short => write!(f, "short"),
+            Standard::Load => write!(f, "50 Ω load"),
+        }
+    }
+}
+
+/// Readings of the open, short and load standards for every step of one sweep range.
+#[derive(Clone, Debug)]
+pub struct Calibration {
+    pub start_freq: i32,
+    pub stop_freq: i32,
+    pub step_count: i32,
+    open: Vec<f32>,
+    short: Vec<f32>,
+    load: Vec<f32>,
+}
+
+impl Calibration {
+    /// Empty calibration of a sweep range stepping up from `start_freq` to `stop_freq`.
+    pub fn new(start_freq: i32, stop_freq: i32, step_count: i32) -> Result<Self> {
+        check_range(start_freq, stop_freq, step_count).map_err(Error::InvalidSweep)?;
+        let points = step_count as usize + 1;
+        Ok(Self {
+            start_freq,
+            stop_freq,
+            step_count,
+            open: vec![f32::NAN; points],
+            short: vec![f32::NAN; points],
+            load: vec![f32::NAN; points],
+        })
+    }
+
+    pub fn matches(&self, start_freq: i32, stop_freq: i32, step_count: i32) -> bool {
+        self.start_freq == start_freq && self.stop_freq == stop_freq && self.step_count == step_count
+    }
+
+    pub fn clear(&mut self, standard: Standard) {
+        self.readings_mut(standard).fill(f32::NAN);
+    }
+
+    pub fn record(&mut self, standard: Standard, index: usize, raw: f32) {
+        if let Some(reading) = self.readings_mut(standard).get_mut(index) {
+            *reading = raw;
+        }
+    }
+
+    /// Whether every step has a reading for `standard`.
+    pub fn is_measured(&self, standard: Standard) -> bool {
+        self.readings(standard).iter().all(|x| !x.is_nan())
+    }
+
+    /// Detector response at step `index`, falling back to the nominal response outside the range.
+    pub fn detector(&self, index: usize) -> Detector {
+        match (self.open.get(index), self.short.get(index), self.load.get(index)) {
+            (Some(open), Some(short), Some(load)) => Detector {
+                zero: *load,
+                full_scale: (open + short) / 2.0,
+            },
+            _ => Detector::default(),
+        }
+    }
+
+    /// Name under which this calibration is stored, derived from the sweep range.
+    pub fn file_name(&self) -> String {
+        format!("{}-{}-{}.{}", self.start_freq, self.stop_freq, self.step_count, EXTENSION)
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<PathBuf> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(self.file_name());
+        let mut f = BufWriter::new(File::create(&path)?);
+        writeln!(f, "{}", HEADER)?;
+        writeln!(f, "range {} {} {}", self.start_freq, self.stop_freq, self.step_count)?;
+        for (i, ((open, short), load)) in self.open.iter().zip(&self.short).zip(&self.load).enumerate() {
+            writeln!(f, "{} {} {} {}", i, open, short, load)?;
+        }
+        f.flush()?;
+        Ok(path)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut lines = BufReader::new(File::open(path)?).lines();
+        if lines.next().transpose()?.as_deref() != Some(HEADER) {
+            return Err(Error::Malformed("missing calibration header".to_string()));
+        }
+        let range = lines.next().transpose()?.unwrap_or_default();
+        let range: Vec<i32> = range.strip_prefix("range ")
+            .ok_or(Error::Malformed("missing sweep range".to_string()))?
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|e| Error::Malformed(format!("sweep range: {e}")))?;
+        let &[start_freq, stop_freq, step_count] = range.as_slice() else {
+            return Err(Error::Malformed("sweep range needs start, stop and step count".to_string()));
+        };
+
+        check_range(start_freq, stop_freq, step_count).map_err(Error::Malformed)?;
+        let mut this = Self::new(start_freq, stop_freq, step_count)?;
+        for line in lines {
+            let line = line?;
+            let fields: Vec<f32> = line.split_whitespace()
+                .map(str::parse)
+                .collect::<std::result::Result<_, _>>()
+                .map_err(|e| Error::Malformed(format!("reading '{line}': {e}")))?;
+            let &[index, open, short, load] = fields.as_slice() else {
+                return Err(Error::Malformed(format!("reading '{line}' needs 4 fields")));
+            };
+            this.record(Standard::Open, index as usize, open);
+            this.record(Standard::Short, index as usize, short);
+            this.record(Standard::Load, index as usize, load);
+        }
+        if !Standard::ALL.iter().all(|s| this.is_measured(*s)) {
+            return Err(Error::Malformed("readings missing for some steps".to_string()));
+        }
+        Ok(this)
+    }
+
+    /// All calibration files stored in `dir`, sorted by name.
+    pub fn list(dir: &Path) -> Result<Vec<PathBuf>> {
+        if !dir.exists() {
+            return Ok(vec![]);
+        }
+        let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == EXTENSION))
+            .collect();
+        files.sort();
+        Ok(files)
+    }
+
+    fn readings(&self, standard: Standard) -> &[f32] {
+        match standard {
+            Standard::Open => &self.open,
+            Standard::Short => &self.short,
+            Standard::Load => &self.load,
+        }
+    }
+
+    fn readings_mut(&mut self, standard: Standard) -> &mut [f32] {
+        match standard {
+            Standard::Open => &mut self.open,
+            Standard::Short => &mut self.short,
+            Standard::Load => &mut self.load,
+        }
+    }
+}
+
+/// Check that a range steps up from start to stop at least once, in steps of whole hertz.
+fn check_range(start_freq: i32, stop_freq: i32, step_count: i32) -> std::result::Result<(), String> {
+    if stop_freq <= start_freq {
+        return Err(format!("stop frequency {stop_freq} Hz is not above the start frequency {start_freq} Hz"));
+    }
+    if step_count < 1 || step_count as i64 > stop_freq as i64 - start_freq as i64 {
+        return Err(format!("{step_count} steps don't fit between {start_freq} and {stop_freq} Hz"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write `contents` to a calibration file of its own and load it.
+    fn load(name: &str, contents: &str) -> Result<Calibration> {
+        let path = std::env::temp_dir().join(format!("swr-analyzer-test-{}-{name}.{EXTENSION}", std::process::id()));
+        fs::write(&path, contents).unwrap();
+        let result = Calibration::load(&path);
+        fs::remove_file(&path).unwrap();
+        result
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut calibration = Calibration::new(1_000_000, 2_000_000, 2).unwrap();
+        for standard in Standard::ALL {
+            for index in 0..3 {
+                calibration.record(standard, index, 100.0 * index as f32 + 1.0);
+            }
+        }
+        let dir = std::env::temp_dir().join(format!("swr-analyzer-test-{}", std::process::id()));
+        let path = calibration.save(&dir).unwrap();
+        let loaded = Calibration::load(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(loaded.matches(1_000_000, 2_000_000, 2));
+        assert_eq!(loaded.open, calibration.open);
+        assert_eq!(loaded.load, calibration.load);
+    }
+
+    #[test]
+    fn reject_bad_ranges() {
+        for (name, range) in [("negative", "1 2 -1"), ("overflow", "1 2 -2"), ("zero", "1000 2000 0"),
+                              ("down", "2000 1000 10"), ("empty", "1000 1000 1"), ("fine", "1000 1010 11")] {
+            let result = load(name, &format!("{HEADER}\nrange {range}\n"));
+            assert!(matches!(result, Err(Error::Malformed(_))), "{range}");
+        }
+    }
+
+    #[test]
+    fn reject_missing_readings() {
+        let result = load("missing", &format!("{HEADER}\nrange 1000 2000 1\n0 1 2 3\n"));
+        assert!(matches!(result, Err(Error::Malformed(_))));
+    }
+}
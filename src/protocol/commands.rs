@@ -1,3 +1,10 @@
+use std::ops::RangeInclusive;
+
+/// Values a command parameter can take: nine characters, including the sign of a negative value
+pub const PARAM_RANGE: RangeInclusive<i32> = -99_999_999..=999_999_999;
+/// Widest step either way, as the step command only takes 8 digits
+pub const MAX_STEP: i32 = 99_999_999;
+
 #[repr(u16)]
 #[derive(Copy, Clone)]
 pub enum CommandOp {
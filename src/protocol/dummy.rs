@@ -1,3 +1,5 @@
+use std::fmt::{Display, Formatter};
+use std::ops::{Add, Div, Mul, Sub};
 use std::thread;
 use std::time::Duration;
 
@@ -5,9 +7,183 @@ use log::{debug, info};
 use rand::{Rng, thread_rng};
 
 use crate::protocol::{error, LedState, SweepParams};
+use crate::protocol::measurement::{ADC_FULL_SCALE, Reflection};
 use crate::protocol::SWRAnalyzer;
 
-pub struct Dummy;
+/// Reference impedance of the analyzer [Ω]
+const SYSTEM_IMPEDANCE: f32 = 50.0;
+/// Radiation resistance of a half wave dipole in free space [Ω]
+const DIPOLE_RESISTANCE: f32 = 73.0;
+const SPEED_OF_LIGHT: f32 = 299_792_458.0;
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum LoadKind {
+    /// Resistor, inductor and capacitor in series
+    #[default]
+    SeriesRlc,
+    /// Half wave dipole, with the radiation resistance rising with frequency
+    Dipole,
+}
+
+impl LoadKind {
+    pub const ALL: [LoadKind; 2] = [LoadKind::SeriesRlc, LoadKind::Dipole];
+}
+
+impl Display for LoadKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadKind::SeriesRlc => write!(f, "Series RLC"),
+            LoadKind::Dipole => write!(f, "Dipole"),
+        }
+    }
+}
+
+/// Antenna on the end of a feedline, as seen by the dummy device.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AntennaModel {
+    pub kind: LoadKind,
+    /// Resonant frequency [Hz]
+    pub resonance: f32,
+    /// Quality factor, higher is a narrower dip
+    pub q: f32,
+    /// Resistance at resonance [Ω], a dipole uses its radiation resistance instead
+    pub resistance: f32,
+    /// Feedline length [m]
+    pub feedline_length: f32,
+    /// Characteristic impedance of the feedline [Ω]
+    pub feedline_impedance: f32,
+    pub velocity_factor: f32,
+    /// Feedline loss at 10 MHz [dB/100 m], growing with the square root of frequency
+    pub feedline_loss: f32,
+    /// Standard deviation of the detector noise [ADC counts]
+    pub noise: f32,
+}
+
+impl Default for AntennaModel {
+    fn default() -> Self {
+        Self {
+            kind: LoadKind::SeriesRlc,
+            resonance: 14_200_000.0,
+            q: 15.0,
+            resistance: 40.0,
+            feedline_length: 10.0,
+            feedline_impedance: 50.0,
+            velocity_factor: 0.66,
+            feedline_loss: 1.5,
+            noise: 1.0,
+        }
+    }
+}
+
+impl AntennaModel {
+    /// Impedance of the antenna itself.
+    fn load_impedance(&self, freq: f32) -> Complex {
+        let ratio = freq / self.resonance;
+        let resistance = match self.kind {
+            LoadKind::SeriesRlc => self.resistance,
+            LoadKind::Dipole => DIPOLE_RESISTANCE * ratio * ratio,
+        };
+        let reactance = self.q * resistance * (ratio - 1.0 / ratio);
+        Complex::new(resistance, reactance)
+    }
+
+    /// Reflection at the analyzer end of the feedline.
+    pub fn reflection(&self, freq: f32) -> Reflection {
+        let z0 = Complex::new(self.feedline_impedance, 0.0);
+        let load = self.load_impedance(freq);
+        let gamma_load = (load - z0) / (load + z0);
+
+        // Travelling down the line and back attenuates and rotates the reflection
+        let loss_db = self.feedline_loss * self.feedline_length / 100.0 * (freq / 10e6).sqrt();
+        let attenuation = 10f32.powf(-2.0 * loss_db / 20.0);
+        let phase = -2.0 * std::f32::consts::TAU * freq * self.feedline_length / (self.velocity_factor * SPEED_OF_LIGHT);
+        let gamma_in = gamma_load * Complex::from_polar(attenuation, phase);
+
+        let one = Complex::new(1.0, 0.0);
+        let input = z0 * (one + gamma_in) / (one - gamma_in);
+        let system = Complex::new(SYSTEM_IMPEDANCE, 0.0);
+        Reflection::new(((input - system) / (input + system)).abs())
+    }
+
+    /// Detector reading at `freq`, including noise.
+    fn sample(&self, freq: f32) -> i32 {
+        let noise = if self.noise > 0.0 {
+            // Sum of uniforms is close enough to a normal distribution
+            let sum: f32 = (0..4).map(|_| thread_rng().gen_range(-1.0..1.0)).sum();
+            sum * self.noise * (3.0f32 / 4.0).sqrt()
+        } else {
+            0.0
+        };
+        let value = self.reflection(freq).magnitude() * ADC_FULL_SCALE + noise;
+        value.round().clamp(0.0, ADC_FULL_SCALE) as i32
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Complex {
+    re: f32,
+    im: f32,
+}
+
+impl Complex {
+    fn new(re: f32, im: f32) -> Self {
+        Self { re, im }
+    }
+
+    fn from_polar(r: f32, theta: f32) -> Self {
+        Self::new(r * theta.cos(), r * theta.sin())
+    }
+
+    fn abs(self) -> f32 {
+        self.re.hypot(self.im)
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(self.re * rhs.re - self.im * rhs.im, self.re * rhs.im + self.im * rhs.re)
+    }
+}
+
+impl Div for Complex {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        let norm = rhs.re * rhs.re + rhs.im * rhs.im;
+        Self::new(
+            (self.re * rhs.re + self.im * rhs.im) / norm,
+            (self.im * rhs.re - self.re * rhs.im) / norm,
+        )
+    }
+}
+
+pub struct Dummy {
+    model: AntennaModel,
+}
+
+impl From<AntennaModel> for Dummy {
+    fn from(model: AntennaModel) -> Self {
+        Self { model }
+    }
+}
 
 impl SWRAnalyzer for Dummy {
     fn version(&mut self) -> crate::protocol::error::Result<String> {
@@ -27,10 +203,9 @@ impl SWRAnalyzer for Dummy {
         let SweepParams { noise_filter, start_freq: start_frequency, step_freq: step_frequency, step_count: max_step_count, step_millis } = params;
         debug!("Settings noise: {noise_filter}, startfreq: {start_frequency}, step: {step_frequency}, step count: {max_step_count}, step delay: {step_millis}");
         'a: loop {
-            let offset = thread_rng().gen_range(0..1000);
             for i in 0..=max_step_count {
                 let cur_freq = start_frequency + step_frequency * i;
-                if f(i, cur_freq, i + offset).is_break() {
+                if f(i, cur_freq, self.model.sample(cur_freq as f32)).is_break() {
                     info!("Scan cancelled");
                     break 'a;
                 }
@@ -40,4 +215,4 @@ impl SWRAnalyzer for Dummy {
         }
         Ok(())
     }
-}
\ No newline at end of file
+}
@@ -4,17 +4,53 @@ use thiserror::Error;
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("i/o error: {0}")]
-    Io(#[from] std::io::Error),
+    Io(std::io::Error),
     #[error("libusb error: {0}")]
     LibUsb(#[from] rusb::Error),
     #[error("device not found")]
     DeviceNotFound,
+    #[error("not supported on this platform")]
+    Unsupported,
     #[error("UTF-8 encoding error in reply")]
     Encoding(#[from] Utf8Error),
     #[error("Unexpected response from device")]
     InvalidResponse,
     #[error("Provided parameter is out of range")]
     OutOfRange,
+    #[error("invalid sweep: {0}")]
+    InvalidSweep(String),
+    #[error("malformed file: {0}")]
+    Malformed(String),
 }
 
-pub type Result<T> = std::result::Result<T, Error>;
\ No newline at end of file
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl From<std::io::Error> for Error {
+    /// USB transfers fail with i/o errors wrapping the libusb error, which is taken out again so
+    /// that an unplugged device can be told apart.
+    fn from(e: std::io::Error) -> Self {
+        match e.get_ref().and_then(|inner| inner.downcast_ref::<rusb::Error>()) {
+            Some(usb) => Error::LibUsb(*usb),
+            None => Error::Io(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use super::*;
+
+    #[test]
+    fn usb_error_through_io() {
+        let error = Error::from(std::io::Error::other(rusb::Error::NoDevice));
+        assert!(matches!(error, Error::LibUsb(rusb::Error::NoDevice)));
+    }
+
+    #[test]
+    fn plain_io_error() {
+        let error = Error::from(std::io::Error::from(ErrorKind::TimedOut));
+        assert!(matches!(error, Error::Io(e) if e.kind() == ErrorKind::TimedOut));
+    }
+}
\ No newline at end of file
@@ -5,7 +5,7 @@ use std::time::Duration;
 use log::info;
 
 use crate::protocol::{error, LedState, SweepParams, SWRAnalyzer};
-use crate::protocol::commands::CommandOp;
+use crate::protocol::commands::{CommandOp, PARAM_RANGE};
 use crate::protocol::error::Error;
 
 pub trait SerialDevice: Read + Write {
@@ -39,7 +39,7 @@ pub trait SerialDevice: Read + Write {
     }
 
     fn send_cmd_param(&mut self, cmd: CommandOp, param: i32) -> error::Result<()> {
-        if !(-99999999..=999999999).contains(&param) || cmd as u16 > 99 {
+        if !PARAM_RANGE.contains(&param) || cmd as u16 > 99 {
             return Err(Error::OutOfRange);
         }
         let mut buff = [0; 32];
@@ -1,14 +1,167 @@
+use std::fmt::{Display, Formatter};
 use std::io::{Read, Write};
+use std::str::FromStr;
+use std::thread;
 use std::time::Duration;
 
-use log::warn;
-use rusb::{DeviceHandle, GlobalContext};
+use log::{error, warn};
+use rusb::{Device, DeviceHandle, GlobalContext, Hotplug, HotplugBuilder, UsbContext};
 
 use crate::protocol::commands::CommandOp;
 use crate::protocol::error::{Error, Result};
 use crate::protocol::foxdelta::SerialDevice;
 
 const TIMEOUT: Duration = Duration::from_millis(2000);
+const VENDOR_ID: u16 = 0x04d8;
+const PRODUCT_ID: u16 = 0xfe00;
+
+/// Identifies one of several attached analyzers.
+///
+/// The serial number survives replugging, the bus address is the fallback for devices without one.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DeviceId {
+    Serial(String),
+    Port {
+        bus: u8,
+        address: u8,
+    },
+}
+
+impl Display for DeviceId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeviceId::Serial(serial) => write!(f, "{}", serial),
+            DeviceId::Port { bus, address } => write!(f, "{}:{}", bus, address),
+        }
+    }
+}
+
+impl FromStr for DeviceId {
+    type Err = std::convert::Infallible;
+
+    /// Parses `bus:address`, anything else is taken as a serial number.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let port = s.split_once(':')
+            .and_then(|(bus, address)| Some(DeviceId::Port { bus: bus.parse().ok()?, address: address.parse().ok()? }));
+        Ok(port.unwrap_or_else(|| DeviceId::Serial(s.to_string())))
+    }
+}
+
+/// An attached Fox-Delta, as found by [`enumerate`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DeviceInfo {
+    pub bus: u8,
+    pub address: u8,
+    pub serial: Option<String>,
+}
+
+impl DeviceInfo {
+    pub fn id(&self) -> DeviceId {
+        match &self.serial {
+            Some(serial) => DeviceId::Serial(serial.clone()),
+            None => DeviceId::Port { bus: self.bus, address: self.address },
+        }
+    }
+}
+
+impl Display for DeviceInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Bus {:03} Device {:03}", self.bus, self.address)?;
+        if let Some(serial) = &self.serial {
+            write!(f, ", serial {}", serial)?;
+        }
+        Ok(())
+    }
+}
+
+/// List every attached Fox-Delta.
+///
+/// Only the descriptors are read, the interface is left unclaimed so devices in use elsewhere and
+/// idle ones aren't disturbed.
+pub fn enumerate() -> Result<Vec<DeviceInfo>> {
+    let devices = fox_delta_devices()?.into_iter().map(|device| {
+        DeviceInfo {
+            bus: device.bus_number(),
+            address: device.address(),
+            serial: read_serial(&device).ok(),
+        }
+    }).collect();
+    Ok(devices)
+}
+
+/// A Fox-Delta appearing on or leaving the bus.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum HotplugEvent {
+    Arrived { bus: u8, address: u8 },
+    Left { bus: u8, address: u8 },
+}
+
+struct HotplugForwarder<F>(F);
+
+impl<F: FnMut(HotplugEvent) + Send> Hotplug<GlobalContext> for HotplugForwarder<F> {
+    fn device_arrived(&mut self, device: Device<GlobalContext>) {
+        (self.0)(HotplugEvent::Arrived { bus: device.bus_number(), address: device.address() })
+    }
+
+    fn device_left(&mut self, device: Device<GlobalContext>) {
+        (self.0)(HotplugEvent::Left { bus: device.bus_number(), address: device.address() })
+    }
+}
+
+/// Call `on_event` from a background thread whenever a Fox-Delta is plugged in or removed.
+///
+/// The callback runs inside libusb event handling and must not talk to devices itself.
+pub fn watch_hotplug(on_event: impl FnMut(HotplugEvent) + Send + 'static) -> Result<()> {
+    if !rusb::has_hotplug() {
+        return Err(Error::Unsupported);
+    }
+    let registration = HotplugBuilder::new()
+        .vendor_id(VENDOR_ID)
+        .product_id(PRODUCT_ID)
+        .register(GlobalContext::default(), Box::new(HotplugForwarder(on_event)))?;
+    thread::spawn(move || {
+        let _registration = registration;
+        loop {
+            if let Err(e) = GlobalContext::default().handle_events(None) {
+                error!("handling usb events: {}", e);
+                return;
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Bus address of the device matching `id`, or of the first one found without an id.
+pub fn locate(id: Option<&DeviceId>) -> Result<DeviceId> {
+    let device = find(id)?;
+    Ok(DeviceId::Port { bus: device.bus_number(), address: device.address() })
+}
+
+fn find(id: Option<&DeviceId>) -> Result<Device<GlobalContext>> {
+    fox_delta_devices()?.into_iter().find(|device| match id {
+        None => true,
+        Some(DeviceId::Port { bus, address }) => device.bus_number() == *bus && device.address() == *address,
+        Some(DeviceId::Serial(serial)) => read_serial(device).is_ok_and(|s| s == *serial),
+    }).ok_or(Error::DeviceNotFound)
+}
+
+fn fox_delta_devices() -> Result<Vec<Device<GlobalContext>>> {
+    let mut devices = vec![];
+    for device in rusb::devices()?.iter() {
+        let descriptor = device.device_descriptor()?;
+        if descriptor.vendor_id() == VENDOR_ID && descriptor.product_id() == PRODUCT_ID {
+            devices.push(device);
+        }
+    }
+    Ok(devices)
+}
+
+/// Serial number from the string descriptor, which needs the device opened but not claimed.
+fn read_serial(device: &Device<GlobalContext>) -> Result<String> {
+    let descriptor = device.device_descriptor()?;
+    let handle = device.open()?;
+    Ok(handle.read_serial_number_string_ascii(&descriptor)?)
+}
 
 pub struct SerialHID {
     handle: DeviceHandle<GlobalContext>,
@@ -31,8 +184,13 @@ impl Write for SerialHID {
 }
 
 impl SerialHID {
-    pub fn new() -> Result<Self>  {
-        let handle = rusb::open_device_with_vid_pid(0x04d8, 0xfe00).ok_or(Error::DeviceNotFound)?;
+    /// Open the device matching `id`, or the first one found without an id.
+    pub fn new(id: Option<&DeviceId>) -> Result<Self>  {
+        Self::open(&find(id)?)
+    }
+
+    fn open(device: &Device<GlobalContext>) -> Result<Self> {
+        let handle = device.open()?;
         handle.set_auto_detach_kernel_driver(true)?;
         handle.claim_interface(0x0)?;
         let this = Self {
@@ -48,4 +206,4 @@ impl Drop for SerialHID {
             warn!("error on drop: {e}");
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,146 @@
+/// Full scale reading of the 10 bit detector ADC.
+pub const ADC_FULL_SCALE: f32 = 1023.0;
+
+/// Magnitude of the reflection coefficient |Γ| seen at the analyzer port.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Reflection(f32);
+
+impl Reflection {
+    pub fn new(magnitude: f32) -> Self {
+        Self(magnitude.clamp(0.0, 1.0))
+    }
+
+    /// Reflection giving a standing wave ratio of `swr`.
+    pub fn from_swr(swr: f32) -> Self {
+        Self::new((swr - 1.0) / (swr + 1.0))
+    }
+
+    pub fn magnitude(self) -> f32 {
+        self.0
+    }
+
+    /// Standing wave ratio, infinite for total reflection.
+    pub fn swr(self) -> f32 {
+        (1.0 + self.0) / (1.0 - self.0)
+    }
+
+    /// Return loss [dB], infinite for a perfect match.
+    pub fn return_loss(self) -> f32 {
+        -20.0 * self.0.log10()
+    }
+
+    /// Power lost to reflection [dB].
+    pub fn mismatch_loss(self) -> f32 {
+        -10.0 * (1.0 - self.0 * self.0).log10()
+    }
+}
+
+/// Linear model of the Fox-Delta bridge detector, mapping a raw reading onto |Γ|.
+///
+/// `zero` is the reading of a matched load and `full_scale` the reading at total reflection.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Detector {
+    pub zero: f32,
+    pub full_scale: f32,
+}
+
+impl Default for Detector {
+    /// Nominal response used when no calibration is available.
+    fn default() -> Self {
+        Self {
+            zero: 0.0,
+            full_scale: ADC_FULL_SCALE,
+        }
+    }
+}
+
+impl Detector {
+    pub fn reflection(&self, raw: f32) -> Reflection {
+        let span = self.full_scale - self.zero;
+        if span <= 0.0 {
+            return Reflection::new(1.0);
+        }
+        Reflection::new((raw - self.zero) / span)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(value: f32, expected: f32) {
+        assert!((value - expected).abs() < 1e-4, "{} instead of {}", value, expected);
+    }
+
+    #[test]
+    fn swr() {
+        assert_close(Reflection::new(0.5).swr(), 3.0);
+        assert_close(Reflection::new(1.0 / 3.0).swr(), 2.0);
+        assert_close(Reflection::new(0.2).swr(), 1.5);
+    }
+
+    #[test]
+    fn return_loss() {
+        assert_close(Reflection::new(0.5).return_loss(), 6.0206);
+        assert_close(Reflection::new(0.1).return_loss(), 20.0);
+        assert_close(Reflection::new(0.01).return_loss(), 40.0);
+    }
+
+    #[test]
+    fn mismatch_loss() {
+        assert_close(Reflection::new(0.5).mismatch_loss(), 1.2494);
+        assert_close(Reflection::new(1.0 / 3.0).mismatch_loss(), 0.5115);
+        assert_close(Reflection::new(0.1).mismatch_loss(), 0.0436);
+    }
+
+    #[test]
+    fn perfect_match() {
+        let matched = Reflection::new(0.0);
+        assert_eq!(matched.swr(), 1.0);
+        assert_eq!(matched.return_loss(), f32::INFINITY);
+        assert_eq!(matched.mismatch_loss(), 0.0);
+    }
+
+    #[test]
+    fn total_reflection() {
+        let open = Reflection::new(1.0);
+        assert_eq!(open.swr(), f32::INFINITY);
+        assert_eq!(open.return_loss(), 0.0);
+        assert_eq!(open.mismatch_loss(), f32::INFINITY);
+        // Approaching it the SWR grows without bound
+        assert!(Reflection::new(0.999).swr() > 1000.0);
+    }
+
+    #[test]
+    fn magnitude_is_clamped() {
+        assert_eq!(Reflection::new(-0.2).magnitude(), 0.0);
+        assert_eq!(Reflection::new(1.5).magnitude(), 1.0);
+    }
+
+    #[test]
+    fn from_swr() {
+        assert_close(Reflection::from_swr(3.0).magnitude(), 0.5);
+        assert_eq!(Reflection::from_swr(1.0).magnitude(), 0.0);
+        for swr in [1.2, 1.5, 2.0, 10.0] {
+            assert_close(Reflection::from_swr(swr).swr(), swr);
+        }
+    }
+
+    #[test]
+    fn detector() {
+        let detector = Detector { zero: 100.0, full_scale: 900.0 };
+        assert_eq!(detector.reflection(100.0).magnitude(), 0.0);
+        assert_eq!(detector.reflection(500.0).magnitude(), 0.5);
+        assert_eq!(detector.reflection(900.0).magnitude(), 1.0);
+        // Readings beyond the calibration are clamped
+        assert_eq!(detector.reflection(50.0).magnitude(), 0.0);
+        assert_eq!(detector.reflection(1000.0).magnitude(), 1.0);
+        assert_close(Detector::default().reflection(ADC_FULL_SCALE / 2.0).magnitude(), 0.5);
+    }
+
+    #[test]
+    fn degenerate_detector_reads_total_reflection() {
+        let detector = Detector { zero: 500.0, full_scale: 500.0 };
+        assert_eq!(detector.reflection(200.0).magnitude(), 1.0);
+    }
+}
@@ -1,16 +1,33 @@
 use std::fmt::Debug;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
 
-use log::error;
+use log::{error, info};
 
-use error::Result;
+use error::{Error, Result};
 
+use crate::protocol::commands::{MAX_STEP, PARAM_RANGE};
+use crate::protocol::dummy::{AntennaModel, Dummy};
+use crate::protocol::foxdelta::FoxDeltaAnalyzer;
+use crate::protocol::libusb::{DeviceId, SerialHID};
+use crate::protocol::simulator::{Faults, Simulator};
+use crate::protocol::tty::SerialPort;
+
+pub mod calibration;
 pub mod libusb;
 pub mod error;
 pub mod foxdelta;
 pub mod dummy;
+pub mod measurement;
+pub mod plan;
+pub mod simulator;
+pub mod tty;
 mod commands;
 
-#[derive(Debug)]
+/// Frequencies the Fox-Delta can generate [Hz]
+pub const FREQ_RANGE: RangeInclusive<i32> = 100_000..=60_000_000;
+
+#[derive(Copy, Clone, Debug)]
 pub struct SweepParams {
     pub noise_filter: i32,
     pub start_freq: i32,
@@ -19,6 +36,41 @@ pub struct SweepParams {
     pub step_millis: i32
 }
 
+impl SweepParams {
+    /// Check the analyzer can sweep with these parameters, before sending any of them.
+    pub fn check(&self) -> Result<()> {
+        let params = [
+            ("noise filter", self.noise_filter),
+            ("start frequency", self.start_freq),
+            ("step frequency", self.step_freq),
+            ("step count", self.step_count),
+            ("step time", self.step_millis),
+        ];
+        if let Some((name, value)) = params.iter().find(|(_, value)| !PARAM_RANGE.contains(value)) {
+            return Err(Error::InvalidSweep(format!("{} {} does not fit in a command", name, value)));
+        }
+        if self.step_freq.abs() > MAX_STEP {
+            return Err(Error::InvalidSweep(format!("step frequency {} is wider than the analyzer can step", self.step_freq)));
+        }
+        if self.step_count < 1 {
+            return Err(Error::InvalidSweep("needs at least one step".to_string()));
+        }
+        if self.step_millis < 0 || self.noise_filter < 0 {
+            return Err(Error::InvalidSweep("step time and noise filter can't be negative".to_string()));
+        }
+        let start_freq = self.start_freq as i64;
+        let stop_freq = start_freq + self.step_freq as i64 * self.step_count as i64;
+        let (low, high) = (start_freq.min(stop_freq), start_freq.max(stop_freq));
+        if low < *FREQ_RANGE.start() as i64 || high > *FREQ_RANGE.end() as i64 {
+            return Err(Error::InvalidSweep(format!(
+                "{}–{} Hz is outside the {}–{} Hz the analyzer covers",
+                low, high, FREQ_RANGE.start(), FREQ_RANGE.end(),
+            )));
+        }
+        Ok(())
+    }
+}
+
 pub trait SWRAnalyzer {
     fn version(&mut self) -> Result<String>;
     fn set_led_blink(&mut self, state: LedState) -> Result<()>;
@@ -32,4 +84,33 @@ pub trait SWRAnalyzer {
 pub enum LedState {
     Off,
     Blink,
-}
\ No newline at end of file
+}
+
+/// How to reach an analyzer.
+#[derive(Clone, Debug)]
+pub enum Connection {
+    /// Built-in device sweeping a modelled antenna
+    Dummy(AntennaModel),
+    /// Fox-Delta on USB, the first one found if no id is given
+    FoxDelta(Option<DeviceId>),
+    /// Fox-Delta compatible firmware on a serial port
+    Serial {
+        path: PathBuf,
+        baud: u32,
+    },
+    /// Simulated Fox-Delta behind the real wire protocol
+    Simulator(Faults),
+}
+
+/// Open the analyzer, returning it with its version string.
+pub fn connect(connection: &Connection) -> Result<(Box<dyn SWRAnalyzer + Send>, String)> {
+    let mut device: Box<dyn SWRAnalyzer + Send> = match connection {
+        Connection::Dummy(model) => Box::new(Dummy::from(*model)),
+        Connection::FoxDelta(id) => Box::new(FoxDeltaAnalyzer::from(SerialHID::new(id.as_ref())?)),
+        Connection::Serial { path, baud } => Box::new(FoxDeltaAnalyzer::from(SerialPort::new(path, *baud)?)),
+        Connection::Simulator(faults) => Box::new(FoxDeltaAnalyzer::from(Simulator::new(*faults))),
+    };
+    let version = device.version()?;
+    info!("version: {}", version);
+    Ok((device, version))
+}
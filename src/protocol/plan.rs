@@ -0,0 +1,256 @@
+use std::ops::ControlFlow;
+
+use crate::protocol::error::Result;
+use crate::protocol::{SweepParams, SWRAnalyzer};
+use crate::protocol::commands::MAX_STEP;
+
+/// Noise filter setting the analyzer software has always used
+pub const DEFAULT_NOISE_FILTER: i32 = 600;
+/// Most steps in one device sweep, as sample frames carry the step index in 16 bits
+const MAX_SEGMENT_STEPS: i32 = u16::MAX as i32;
+
+/// Device sweeps that together step from the start to exactly the stop frequency.
+///
+/// The analyzer only steps in whole hertz, so the step count is the one nearest to the requested
+/// count that divides the span, and the last point lands on the stop frequency. Sweeps with more
+/// points than the analyzer can number are split into several device sweeps of at least one step
+/// each.
+#[derive(Clone, Debug)]
+pub struct SweepPlan {
+    pub segments: Vec<SweepParams>,
+}
+
+impl SweepPlan {
+    /// Plan about `step_count` steps from `start_freq` to `stop_freq`, see [`SweepPlan::step_count`]
+    /// for the number actually taken.
+    pub fn new(start_freq: i32, stop_freq: i32, step_count: i32, step_millis: i32, noise_filter: i32) -> Self {
+        let span = stop_freq as i64 - start_freq as i64;
+        let count = dividing_count(span.abs(), step_count as i64);
+        let sweep = SweepParams {
+            noise_filter,
+            start_freq,
+            step_freq: (span / count) as i32,
+            step_count: count as i32,
+            step_millis,
+        };
+        Self { segments: split(sweep).collect() }
+    }
+
+    /// Steps over all segments, which differs from the requested count when that doesn't divide the span.
+    pub fn step_count(&self) -> i32 {
+        self.point_count() as i32 - 1
+    }
+
+    /// Number of samples over all segments.
+    pub fn point_count(&self) -> usize {
+        self.segments.iter().map(|s| s.step_count as usize + 1).sum()
+    }
+
+    /// Overall sweep, with the step of the first segment, for describing the measurement.
+    pub fn params(&self) -> SweepParams {
+        SweepParams {
+            step_count: self.step_count(),
+            ..self.segments[0]
+        }
+    }
+
+    /// How far a sweep has got once the sample at `index` arrived.
+    pub fn progress(&self, index: usize) -> Progress {
+        let mut first = 0;
+        let segment = self.segments.iter()
+            .position(|s| {
+                first += s.step_count as usize + 1;
+                index < first
+            })
+            .unwrap_or(self.segments.len() - 1);
+        Progress {
+            segment: segment + 1,
+            segments: self.segments.len(),
+            points: (index + 1).min(self.point_count()),
+            total: self.point_count(),
+        }
+    }
+
+    pub fn check(&self) -> Result<()> {
+        self.segments.iter().try_for_each(SweepParams::check)
+    }
+
+    /// Sweep every segment in turn, numbering the samples across segments.
+    ///
+    /// A single segment runs continuously on the device itself, several are repeated as
+    /// oneshot sweeps until `f` breaks.
+    pub fn run(&self,
+               device: &mut dyn SWRAnalyzer,
+               continuous: bool,
+               f: &mut dyn FnMut(i32, i32, i32) -> ControlFlow<()>) -> Result<()> {
+        if let [segment] = self.segments.as_slice() {
+            return device.start_sweep(continuous, *segment, f);
+        }
+        let mut cancelled = false;
+        loop {
+            let mut offset = 0;
+            for segment in &self.segments {
+                device.start_sweep(false, *segment, &mut |i, freq, value| {
+                    let flow = f(offset + i, freq, value);
+                    cancelled |= flow.is_break();
+                    flow
+                })?;
+                if cancelled {
+                    return Ok(());
+                }
+                offset += segment.step_count + 1;
+            }
+            if !continuous {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Sweep progress, counting from one.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Progress {
+    pub segment: usize,
+    pub segments: usize,
+    pub points: usize,
+    pub total: usize,
+}
+
+/// Step count dividing `span` nearest to `wanted`, with steps no finer than a hertz and no wider
+/// than the step command takes, preferring the finer sweep between two equally near.
+fn dividing_count(span: i64, wanted: i64) -> i64 {
+    if span == 0 {
+        return 1;
+    }
+    let fewest = (span + MAX_STEP as i64 - 1) / MAX_STEP as i64;
+    let wanted = wanted.clamp(fewest, span);
+    (1..)
+        .take_while(|d| d * d <= span)
+        .filter(|d| span % d == 0)
+        .flat_map(|d| [d, span / d])
+        .filter(|&count| count >= fewest)
+        .min_by_key(|&count| ((count - wanted).abs(), -count))
+        .unwrap_or(span)
+}
+
+/// Cut a device sweep into pieces the analyzer can number, continuing where the last one ended.
+///
+/// The points are shared out evenly, so no piece is left with a single point and no step.
+fn split(segment: SweepParams) -> impl Iterator<Item=SweepParams> {
+    let points = segment.step_count as i64 + 1;
+    let chunk = MAX_SEGMENT_STEPS as i64 + 1;
+    let pieces = (points + chunk - 1) / chunk;
+    (0..pieces).map(move |piece| {
+        let first = points * piece / pieces;
+        let end = points * (piece + 1) / pieces;
+        SweepParams {
+            start_freq: segment.start_freq + segment.step_freq * first as i32,
+            step_count: (end - first - 1) as i32,
+            ..segment
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan(start_freq: i32, stop_freq: i32, step_count: i32) -> SweepPlan {
+        SweepPlan::new(start_freq, stop_freq, step_count, 10, DEFAULT_NOISE_FILTER)
+    }
+
+    /// Frequency of every point, in the order they are swept.
+    fn freqs(plan: &SweepPlan) -> Vec<i64> {
+        plan.segments.iter()
+            .flat_map(|s| (0..=s.step_count as i64).map(|i| s.start_freq as i64 + s.step_freq as i64 * i))
+            .collect()
+    }
+
+    #[test]
+    fn divisible_span() {
+        let plan = plan(1_000_000, 35_000_000, 100);
+        assert_eq!(plan.segments.len(), 1);
+        assert_eq!(plan.point_count(), 101);
+        assert_eq!(*freqs(&plan).last().unwrap(), 35_000_000);
+        plan.check().unwrap();
+    }
+
+    #[test]
+    fn indivisible_span_lands_on_stop() {
+        let plan = plan(7_000_000, 7_200_000, 333);
+        assert_eq!(plan.segments.len(), 1);
+        // 320 is the divisor of the 200 kHz span nearest to 333
+        assert_eq!(plan.step_count(), 320);
+        assert_eq!(plan.segments[0].step_freq, 625);
+        assert_eq!(*freqs(&plan).last().unwrap(), 7_200_000);
+        plan.check().unwrap();
+    }
+
+    #[test]
+    fn prime_span_lands_on_stop() {
+        let plan = plan(1_000_000, 1_000_127, 10);
+        assert_eq!(plan.step_count(), 1);
+        assert_eq!(*freqs(&plan).last().unwrap(), 1_000_127);
+    }
+
+    #[test]
+    fn finer_of_equally_near_counts() {
+        assert_eq!(dividing_count(99, 10), 11);
+        assert_eq!(dividing_count(12, 5), 6);
+        assert_eq!(dividing_count(12, 12), 12);
+    }
+
+    #[test]
+    fn downward_sweep() {
+        let plan = plan(7_200_000, 7_000_000, 333);
+        assert_eq!(plan.segments[0].step_freq, -625);
+        assert_eq!(*freqs(&plan).last().unwrap(), 7_000_000);
+    }
+
+    #[test]
+    fn planned_count_plans_the_same() {
+        let count = plan(7_000_000, 7_200_000, 333).step_count();
+        assert_eq!(plan(7_000_000, 7_200_000, count).step_count(), count);
+    }
+
+    #[test]
+    fn no_finer_than_a_hertz() {
+        let plan = plan(1_000_000, 1_000_010, 100);
+        assert_eq!(plan.point_count(), 11);
+        assert_eq!(*freqs(&plan).last().unwrap(), 1_000_010);
+    }
+
+    #[test]
+    fn split_beyond_step_index() {
+        let whole = plan(1_000_000, 1_065_535, 65_535);
+        assert_eq!(whole.segments.len(), 1);
+        assert_eq!(whole.point_count(), 65_536);
+
+        let plan = plan(1_000_000, 1_065_536, 65_536);
+        assert_eq!(plan.segments.len(), 2);
+        assert_eq!(plan.point_count(), 65_537);
+        assert!(plan.segments.iter().all(|s| (1..=MAX_SEGMENT_STEPS).contains(&s.step_count)));
+        // The pieces continue each other without gaps or repeats
+        let freqs = freqs(&plan);
+        assert!(freqs.windows(2).all(|w| w[1] - w[0] == 1));
+        assert_eq!(*freqs.last().unwrap(), 1_065_536);
+        plan.check().unwrap();
+    }
+
+    #[test]
+    fn progress_across_segments() {
+        let plan = plan(1_000_000, 1_065_536, 65_536);
+        let first = plan.segments[0].step_count as usize + 1;
+        assert_eq!(plan.progress(first - 1).segment, 1);
+        assert_eq!(plan.progress(first).segment, 2);
+        assert_eq!(plan.progress(65_536).points, 65_537);
+    }
+
+    #[test]
+    fn wide_steps_take_more_steps() {
+        let plan = plan(0, 300_000_000, 2);
+        assert_eq!(plan.segments.len(), 1);
+        assert!(plan.segments[0].step_freq <= MAX_STEP);
+        assert_eq!(*freqs(&plan).last().unwrap(), 300_000_000);
+    }
+}
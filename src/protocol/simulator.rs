@@ -0,0 +1,346 @@
+use std::collections::VecDeque;
+use std::io;
+use std::io::{ErrorKind, Read, Write};
+use std::thread;
+use std::time::Duration;
+
+use log::{debug, warn};
+use rand::{Rng, thread_rng};
+
+use crate::protocol::commands::CommandOp;
+use crate::protocol::foxdelta::SerialDevice;
+use crate::protocol::measurement::ADC_FULL_SCALE;
+
+const FRAME_LEN: usize = 32;
+const VERSION: &str = "Simulator 1.0";
+/// Centre and half width of the simulated antenna dip [Hz]
+const RESONANCE: f32 = 14_200_000.0;
+const BANDWIDTH: f32 = 300_000.0;
+
+/// Errors to inject into the simulated link, each as a probability per frame from 0 to 1.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Faults {
+    /// Sample frame with a broken header
+    pub bad_frame: f64,
+    /// Reply lost, the read times out
+    pub timeout: f64,
+    /// Acknowledge answered with garbage, making the host retry
+    pub nak: f64,
+}
+
+/// In-memory stand-in for a Fox-Delta speaking the real wire format.
+///
+/// Commands are `:NN\r` or `:NNparam\r`, padded with zeros to 32 bytes. Only `:99` and
+/// acknowledged commands are answered directly; during a sweep every sample frame is sent
+/// after the host acknowledges the previous one with `:\r`.
+pub struct Simulator {
+    faults: Faults,
+    /// Bytes written but not yet terminated by `\r`
+    command: Vec<u8>,
+    /// Frames waiting to be read, the first one possibly partially
+    replies: VecDeque<[u8; FRAME_LEN]>,
+    read_offset: usize,
+    start_freq: i32,
+    step_freq: i32,
+    step_count: i32,
+    step_millis: i32,
+    sweep: Option<Sweep>,
+}
+
+#[derive(Copy, Clone)]
+struct Sweep {
+    continuous: bool,
+    index: i32,
+}
+
+impl Simulator {
+    pub fn new(faults: Faults) -> Self {
+        Self {
+            faults,
+            command: vec![],
+            replies: VecDeque::new(),
+            read_offset: 0,
+            start_freq: 0,
+            step_freq: 0,
+            step_count: 0,
+            step_millis: 0,
+            sweep: None,
+        }
+    }
+
+    fn execute(&mut self, command: &str) {
+        let Some(command) = command.strip_prefix(':') else {
+            debug!("simulator: ignoring '{}'", command);
+            return;
+        };
+        if command.is_empty() {
+            self.next_sample();
+            return;
+        }
+        // Parameters always have 9 digits, so only a bare command can have 3 digits
+        let (op, param) = match command.len() {
+            2 | 3 => (command, None),
+            _ => (&command[..2], Some(&command[2..])),
+        };
+        let (Ok(op), Ok(param)) = (op.parse::<u16>(), param.map(str::parse::<i32>).transpose()) else {
+            debug!("simulator: malformed command ':{}'", command);
+            return;
+        };
+        let param = param.unwrap_or_default();
+
+        match op {
+            99 => self.reply(&format!(":99{}\r", VERSION)),
+            op if op == CommandOp::Exit as u16 => {
+                if self.fault(self.faults.nak) {
+                    self.reply(":NAK\r");
+                } else {
+                    self.reply(&format!(":ACK {:02}\r", op));
+                }
+            }
+            op if op == CommandOp::StartFrequency as u16 => self.start_freq = param,
+            op if op == CommandOp::StepFrequency as u16 => self.step_freq = param,
+            op if op == CommandOp::StepCount as u16 => self.step_count = param,
+            op if op == CommandOp::StepTimeMillis as u16 => self.step_millis = param,
+            op if op == CommandOp::SweepEnable as u16 || op == CommandOp::SweepOneshot as u16 => {
+                self.replies.clear();
+                self.read_offset = 0;
+                self.sweep = Some(Sweep {
+                    continuous: op == CommandOp::SweepEnable as u16,
+                    index: 0,
+                });
+                self.next_sample();
+            }
+            op if op == CommandOp::SweepDisable as u16 => {
+                if self.sweep.take().is_some() {
+                    // Whatever was still queued is superseded by the end of the sweep
+                    self.replies.clear();
+                    self.read_offset = 0;
+                    self.replies.push_back(sample_frame(&[]));
+                }
+            }
+            _ => debug!("simulator: command {} {}", op, param),
+        }
+    }
+
+    /// Queue the frame for the current step, or the empty frame ending the sweep.
+    fn next_sample(&mut self) {
+        let Some(sweep) = &mut self.sweep else {
+            return;
+        };
+        if sweep.index > self.step_count {
+            if sweep.continuous {
+                sweep.index = 0;
+            } else {
+                self.sweep = None;
+                self.replies.push_back(sample_frame(&[]));
+                return;
+            }
+        }
+
+        let index = sweep.index;
+        sweep.index += 1;
+        let freq = self.start_freq + self.step_freq * index;
+        let mut frame = sample_frame(&[index as u16, response(freq)]);
+        if self.fault(self.faults.bad_frame) {
+            frame[0] = b'?';
+        }
+        self.replies.push_back(frame);
+    }
+
+    fn reply(&mut self, reply: &str) {
+        let mut frame = [0; FRAME_LEN];
+        frame[..reply.len()].copy_from_slice(reply.as_bytes());
+        self.replies.push_back(frame);
+    }
+
+    fn fault(&self, probability: f64) -> bool {
+        probability > 0.0 && thread_rng().gen_bool(probability.min(1.0))
+    }
+}
+
+impl Read for Simulator {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.read_offset == 0 && self.fault(self.faults.timeout) {
+            self.replies.pop_front();
+        }
+        let Some(frame) = self.replies.front() else {
+            return Err(io::Error::from(ErrorKind::TimedOut));
+        };
+        if self.read_offset == 0 && frame[0] == b':' && self.sweep.is_some() {
+            thread::sleep(Duration::from_millis(self.step_millis.max(0) as u64));
+        }
+
+        let n = buf.len().min(FRAME_LEN - self.read_offset);
+        buf[..n].copy_from_slice(&frame[self.read_offset..self.read_offset + n]);
+        self.read_offset += n;
+        if self.read_offset == FRAME_LEN {
+            self.replies.pop_front();
+            self.read_offset = 0;
+        }
+        Ok(n)
+    }
+}
+
+impl Write for Simulator {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &byte in buf {
+            match byte {
+                b'\r' => {
+                    let command = String::from_utf8_lossy(&self.command).into_owned();
+                    self.command.clear();
+                    self.execute(&command);
+                }
+                // Padding after the terminator
+                0 => {}
+                byte => self.command.push(byte),
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for Simulator {
+    fn drop(&mut self) {
+        // Like a real port, hand the device back with the exit command
+        if let Err(e) = self.send_ack(CommandOp::Exit) {
+            warn!("error on drop: {e}");
+        }
+    }
+}
+
+/// Frame as decoded by the host: `:`, a type byte, the value count and up to three values.
+fn sample_frame(values: &[u16]) -> [u8; FRAME_LEN] {
+    let mut frame = [0; FRAME_LEN];
+    frame[0] = b':';
+    frame[2..4].copy_from_slice(&(values.len() as u16).to_le_bytes());
+    for (i, value) in values.iter().enumerate() {
+        frame[4 + 2 * i..6 + 2 * i].copy_from_slice(&value.to_le_bytes());
+    }
+    frame[10] = b'\r';
+    frame
+}
+
+/// Detector reading for a single resonant dip, with a little noise.
+fn response(freq: i32) -> u16 {
+    let detuning = (freq as f32 - RESONANCE) / BANDWIDTH;
+    let reflection = 1.0 - 0.9 / (1.0 + detuning * detuning);
+    let noise = thread_rng().gen_range(-2.0..2.0);
+    (reflection * ADC_FULL_SCALE + noise).clamp(0.0, ADC_FULL_SCALE) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
+
+    use crate::protocol::error::{Error, Result};
+    use crate::protocol::foxdelta::FoxDeltaAnalyzer;
+    use crate::protocol::{SweepParams, SWRAnalyzer};
+
+    use super::*;
+
+    const PARAMS: SweepParams = SweepParams {
+        noise_filter: 600,
+        start_freq: 14_000_000,
+        step_freq: 50_000,
+        step_count: 8,
+        step_millis: 0,
+    };
+
+    fn analyzer(faults: Faults) -> FoxDeltaAnalyzer<Simulator> {
+        FoxDeltaAnalyzer::from(Simulator::new(faults))
+    }
+
+    /// Sweep `PARAMS` once, stopping after `limit` samples.
+    fn sweep(analyzer: &mut FoxDeltaAnalyzer<Simulator>, limit: usize) -> Result<Vec<(i32, i32, i32)>> {
+        let mut samples = vec![];
+        analyzer.start_sweep(false, PARAMS, &mut |index, freq, value| {
+            samples.push((index, freq, value));
+            if samples.len() < limit { ControlFlow::Continue(()) } else { ControlFlow::Break(()) }
+        })?;
+        Ok(samples)
+    }
+
+    #[test]
+    fn version() {
+        assert_eq!(analyzer(Faults::default()).version().unwrap(), VERSION);
+    }
+
+    #[test]
+    fn oneshot_sweep() {
+        let samples = sweep(&mut analyzer(Faults::default()), usize::MAX).unwrap();
+        assert_eq!(samples.len(), PARAMS.step_count as usize + 1);
+        for (i, &(index, freq, value)) in samples.iter().enumerate() {
+            assert_eq!(index, i as i32);
+            assert_eq!(freq, PARAMS.start_freq + PARAMS.step_freq * i as i32);
+            assert!((0..=ADC_FULL_SCALE as i32).contains(&value));
+        }
+    }
+
+    #[test]
+    fn cancel_mid_sweep() {
+        let mut analyzer = analyzer(Faults::default());
+        let samples = sweep(&mut analyzer, 3).unwrap();
+        assert_eq!(samples.iter().map(|s| s.0).collect::<Vec<_>>(), [0, 1, 2]);
+        // The link is back in step for the next command
+        assert_eq!(analyzer.version().unwrap(), VERSION);
+    }
+
+    #[test]
+    fn bad_frame() {
+        let faults = Faults { bad_frame: 1.0, ..Faults::default() };
+        assert!(matches!(sweep(&mut analyzer(faults), usize::MAX), Err(Error::InvalidResponse)));
+    }
+
+    #[test]
+    fn timeout() {
+        let faults = Faults { timeout: 1.0, ..Faults::default() };
+        let result = analyzer(faults).version();
+        assert!(matches!(result, Err(Error::Io(e)) if e.kind() == ErrorKind::TimedOut));
+    }
+
+    /// Simulator refusing the first `naks` commands, counting the commands written.
+    struct Refusing {
+        simulator: Simulator,
+        naks: usize,
+        writes: usize,
+    }
+
+    impl Read for Refusing {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.simulator.read(buf)
+        }
+    }
+
+    impl Write for Refusing {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.writes += 1;
+            self.simulator.faults.nak = if self.writes <= self.naks { 1.0 } else { 0.0 };
+            self.simulator.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.simulator.flush()
+        }
+    }
+
+    #[test]
+    fn nak_then_retry() {
+        let mut device = Refusing { simulator: Simulator::new(Faults::default()), naks: 1, writes: 0 };
+        device.send_ack(CommandOp::Exit).unwrap();
+        assert_eq!(device.writes, 2);
+    }
+
+    #[test]
+    fn nak_gives_up() {
+        let mut device = Refusing { simulator: Simulator::new(Faults::default()), naks: usize::MAX, writes: 0 };
+        assert!(matches!(device.send_ack(CommandOp::Exit), Err(Error::InvalidResponse)));
+        assert_eq!(device.writes, 3);
+    }
+}
@@ -0,0 +1,94 @@
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{ErrorKind, Read, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+
+use log::warn;
+
+use crate::protocol::commands::CommandOp;
+use crate::protocol::error::{Error, Result};
+use crate::protocol::foxdelta::SerialDevice;
+
+/// Read timeout [0.1 s], matching the USB transport
+const TIMEOUT_DECISECONDS: libc::cc_t = 20;
+
+/// Fox-Delta compatible firmware behind a USB-serial bridge or CDC-ACM port.
+pub struct SerialPort {
+    file: File,
+}
+
+impl Read for SerialPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.file.read(buf)? {
+            // VMIN = 0 makes a read return nothing once VTIME expires
+            0 if !buf.is_empty() => Err(io::Error::from(ErrorKind::TimedOut)),
+            n => Ok(n),
+        }
+    }
+}
+
+impl Write for SerialPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl SerialPort {
+    pub fn new(path: &Path, baud: u32) -> Result<Self> {
+        let speed = baud_rate(baud).ok_or(Error::OutOfRange)?;
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NOCTTY)
+            .open(path)?;
+
+        let fd = file.as_raw_fd();
+        // SAFETY: fd is a valid open file descriptor and termios is fully initialised by tcgetattr
+        unsafe {
+            let mut termios: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(fd, &mut termios) != 0 {
+                return Err(io::Error::last_os_error().into());
+            }
+            libc::cfmakeraw(&mut termios);
+            termios.c_cflag |= libc::CLOCAL | libc::CREAD;
+            termios.c_cc[libc::VMIN] = 0;
+            termios.c_cc[libc::VTIME] = TIMEOUT_DECISECONDS;
+            if libc::cfsetspeed(&mut termios, speed) != 0
+                || libc::tcsetattr(fd, libc::TCSANOW, &termios) != 0
+                || libc::tcflush(fd, libc::TCIOFLUSH) != 0 {
+                return Err(io::Error::last_os_error().into());
+            }
+        }
+
+        Ok(Self { file })
+    }
+}
+
+impl Drop for SerialPort {
+    fn drop(&mut self) {
+        if let Err(e) = self.send_ack(CommandOp::Exit) {
+            warn!("error on drop: {e}");
+        }
+    }
+}
+
+fn baud_rate(baud: u32) -> Option<libc::speed_t> {
+    let speed = match baud {
+        9600 => libc::B9600,
+        19200 => libc::B19200,
+        38400 => libc::B38400,
+        57600 => libc::B57600,
+        115200 => libc::B115200,
+        230400 => libc::B230400,
+        460800 => libc::B460800,
+        921600 => libc::B921600,
+        _ => return None,
+    };
+    Some(speed)
+}
@@ -0,0 +1,238 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use gtk4::glib;
+use gtk4::glib::Propagation;
+use log::{error, info};
+use relm4::prelude::*;
+use relm4::prelude::gtk::prelude::*;
+
+use crate::protocol::calibration::{Calibration, Standard};
+use crate::protocol::plan::SweepPlan;
+use crate::ui::swr_worker::{Sample, State, STATE};
+
+/// Directory the calibration sets are stored in.
+pub(super) fn calibration_dir() -> PathBuf {
+    glib::user_data_dir().join("swr-analyzer").join("calibration")
+}
+
+pub(super) struct CalibrationWizard {
+    visible: bool,
+    calibration: Option<Calibration>,
+    step_millis: i32,
+    noise_filter: i32,
+    step: Step,
+    state: State,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Step {
+    Connect(Standard),
+    Measuring(Standard),
+    Done,
+}
+
+#[derive(Debug)]
+pub(super) enum Input {
+    Open {
+        start_freq: i32,
+        stop_freq: i32,
+        step_count: i32,
+        step_millis: i32,
+        noise_filter: i32,
+    },
+    Measure,
+    Sample(Sample),
+    SweepDone,
+    Save,
+    Close,
+    StateChange(State),
+}
+
+#[derive(Debug)]
+pub(super) enum Output {
+    Sweep(SweepPlan),
+    Cancel,
+    Saved {
+        path: PathBuf,
+        calibration: Arc<Calibration>,
+    },
+}
+
+#[relm4::component(pub(super))]
+//noinspection RsSortImplTraitMembers
+impl Component for CalibrationWizard {
+    type CommandOutput = ();
+    type Input = Input;
+    type Output = Output;
+    type Init = gtk::Window;
+
+    view! {
+        gtk::Window {
+            set_title: Some("Calibration"),
+            set_default_size: (400, 150),
+            set_modal: true,
+            set_transient_for: Some(&window),
+            #[watch]
+            set_visible: model.visible,
+
+            gtk::Box {
+                set_orientation: gtk::Orientation::Vertical,
+                set_margin_all: 10,
+                set_spacing: 10,
+
+                gtk::Label {
+                    set_wrap: true,
+                    set_vexpand: true,
+                    #[watch]
+                    set_label: &model.instructions(),
+                },
+                gtk::Box {
+                    set_orientation: gtk::Orientation::Horizontal,
+                    set_halign: gtk::Align::End,
+                    set_spacing: 5,
+
+                    gtk::Button {
+                        set_label: "Cancel",
+                        connect_clicked => Input::Close,
+                    },
+                    gtk::Button {
+                        set_label: "Measure",
+                        #[watch]
+                        set_visible: model.step != Step::Done,
+                        #[watch]
+                        set_sensitive: matches!(model.step, Step::Connect(_)) && model.state == State::Idle,
+                        connect_clicked => Input::Measure,
+                    },
+                    gtk::Button {
+                        set_label: "Save",
+                        #[watch]
+                        set_visible: model.step == Step::Done,
+                        connect_clicked => Input::Save,
+                    },
+                },
+            },
+
+            connect_close_request[sender] => move |_| {
+                sender.input(Input::Close);
+                Propagation::Stop
+            }
+        }
+    }
+
+    fn init(window: Self::Init, root: Self::Root, sender: ComponentSender<Self>) -> ComponentParts<Self> {
+        let model = Self {
+            visible: false,
+            calibration: None,
+            step_millis: 0,
+            noise_filter: 0,
+            step: Step::Connect(Standard::Open),
+            state: State::Disconnected,
+        };
+
+        let widgets = view_output!();
+
+        STATE.subscribe(sender.input_sender(), |state| Input::StateChange(*state));
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>, _root: &Self::Root) {
+        match message {
+            Input::Open { start_freq, stop_freq, step_count, step_millis, noise_filter } => {
+                self.calibration = match Calibration::new(start_freq, stop_freq, step_count) {
+                    Ok(calibration) => Some(calibration),
+                    Err(e) => {
+                        error!("can't calibrate: {}", e);
+                        return;
+                    }
+                };
+                self.step_millis = step_millis;
+                self.noise_filter = noise_filter;
+                self.step = Step::Connect(Standard::Open);
+                self.visible = true;
+            }
+            Input::Measure => {
+                let (Step::Connect(standard), Some(calibration)) = (self.step, &mut self.calibration) else {
+                    return;
+                };
+                calibration.clear(standard);
+                self.step = Step::Measuring(standard);
+                sender.output(Output::Sweep(SweepPlan::new(
+                    calibration.start_freq,
+                    calibration.stop_freq,
+                    calibration.step_count,
+                    self.step_millis,
+                    self.noise_filter,
+                ))).unwrap();
+            }
+            Input::Sample(sample) => {
+                if let (Step::Measuring(standard), Some(calibration)) = (self.step, &mut self.calibration) {
+                    calibration.record(standard, sample.index, sample.value);
+                }
+            }
+            Input::SweepDone => {
+                let (Step::Measuring(standard), Some(calibration)) = (self.step, &self.calibration) else {
+                    return;
+                };
+                if !calibration.is_measured(standard) {
+                    error!("{} measurement incomplete, measure again", standard);
+                    self.step = Step::Connect(standard);
+                    return;
+                }
+                self.step = match standard {
+                    Standard::Open => Step::Connect(Standard::Short),
+                    Standard::Short => Step::Connect(Standard::Load),
+                    Standard::Load => Step::Done,
+                };
+            }
+            Input::Save => {
+                let Some(calibration) = self.calibration.take() else {
+                    return;
+                };
+                match calibration.save(&calibration_dir()) {
+                    Ok(path) => {
+                        info!("calibration saved to {}", path.display());
+                        sender.output(Output::Saved {
+                            path,
+                            calibration: Arc::new(calibration),
+                        }).unwrap();
+                    }
+                    Err(e) => error!("saving calibration: {}", e),
+                }
+                self.visible = false;
+            }
+            Input::Close => {
+                if matches!(self.step, Step::Measuring(_)) {
+                    sender.output(Output::Cancel).unwrap();
+                }
+                self.calibration = None;
+                self.visible = false;
+            }
+            Input::StateChange(state) => {
+                self.state = state;
+            }
+        }
+    }
+}
+
+impl CalibrationWizard {
+    fn instructions(&self) -> String {
+        match self.step {
+            Step::Connect(standard) => format!(
+                "Connect the {} standard to the analyzer port and press Measure.",
+                standard
+            ),
+            Step::Measuring(standard) => format!("Measuring {} standard...", standard),
+            Step::Done => match &self.calibration {
+                Some(c) => format!(
+                    "Calibration of {:.3} - {:.3} MHz with {} steps complete.",
+                    c.start_freq as f32 / 1000000.0,
+                    c.stop_freq as f32 / 1000000.0,
+                    c.step_count
+                ),
+                None => String::new(),
+            },
+        }
+    }
+}
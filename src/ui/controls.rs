@@ -1,38 +1,108 @@
+use std::path::{Path, PathBuf};
+
 use log::error;
 use relm4::prelude::*;
 use relm4::prelude::gtk::prelude::*;
 
+use crate::bands::{amateur_bands, Band, format_custom, parse_custom, Region};
+use crate::frequency;
+use crate::protocol::calibration::Calibration;
+use crate::protocol::{Connection, FREQ_RANGE};
+use crate::protocol::libusb::{DeviceId, DeviceInfo};
+use crate::protocol::plan::{DEFAULT_NOISE_FILTER, SweepPlan};
+use crate::protocol::simulator::Faults;
+use crate::ui::calibration::calibration_dir;
+use crate::ui::settings;
 use crate::ui::swr_worker::{State, STATE};
 
 pub(super) struct Controls {
+    /// Start frequency, or the centre in centre/span mode
     start_freq: gtk::EntryBuffer,
+    /// Stop frequency, or the span in centre/span mode
     stop_freq: gtk::EntryBuffer,
+    centre_span: bool,
+    /// Start and stop frequency of the entries while they are valid, kept exact as the centre
+    /// shown in centre/span mode is rounded
+    range: Option<(i32, i32)>,
+    /// First problem with the sweep entries, shown next to the entry
+    invalid: Option<Invalid>,
+    /// Why the sweep takes another step count than the one entered
+    step_note: Option<String>,
     step_count: gtk::EntryBuffer,
     step_millis: gtk::EntryBuffer,
+    noise_filter: gtk::EntryBuffer,
+    serial_port: gtk::EntryBuffer,
+    baud_rate: gtk::EntryBuffer,
+    region: Region,
+    /// Ranges named by the user, listed after the amateur bands
+    custom_bands: Vec<Band>,
+    band_dropdown: gtk::DropDown,
+    band_names: gtk::StringList,
+    band_name: gtk::EntryBuffer,
+    device_dropdown: gtk::DropDown,
+    device_names: gtk::StringList,
+    devices: Vec<DeviceInfo>,
+    calibration_dropdown: gtk::DropDown,
+    calibrations: gtk::StringList,
+    calibration_files: Vec<PathBuf>,
+    calibration: Option<PathBuf>,
     state: State,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub(super) enum Input {
     Continuous,
     Oneshot,
+    Calibrate,
+    ConnectSerial,
+    ConnectDevice,
+    Devices(Vec<DeviceInfo>),
+    SelectCalibration(u32),
+    CalibrationSaved(PathBuf),
+    Restore(Vec<(String, String)>),
+    SetCentreSpan(bool),
+    /// Check the sweep entries after an edit
+    Validate,
+    SelectRegion(u32),
+    SelectBand(u32),
+    /// Remember the start and stop frequency as a named band
+    SaveBand,
+    RemoveBand,
+    /// Sweep a new range once, keeping the step count and time
+    SweepRange {
+        start_freq: i32,
+        stop_freq: i32,
+    },
     StateChange(State),
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub(super) enum Output {
-    Connect {
-        dummy: bool,
-    },
+    Connect(Connection),
+    /// Choose the antenna model before connecting the dummy
+    ConfigureDummy,
     Disconnect,
+    /// List the attached analyzers again
+    Enumerate,
     Start {
         continuous: bool,
         start_freq: i32,
         stop_freq: i32,
         step_count: i32,
         step_millis: i32,
+        noise_filter: i32,
     },
     Cancel,
+    Calibrate {
+        start_freq: i32,
+        stop_freq: i32,
+        step_count: i32,
+        step_millis: i32,
+        noise_filter: i32,
+    },
+    LoadCalibration(Option<PathBuf>),
+    /// Bands to show on the graph
+    Bands(Vec<Band>),
     Udev,
 }
 
@@ -46,74 +116,220 @@ impl SimpleComponent for Controls {
     view! {
         gtk::Grid {
             attach[0, 0, 1, 1]= &gtk::Label {
-                set_label: "Start frequency [Hz]:",
+                set_label: "Band plan:",
+            },
+            attach[1, 0, 1, 1]= &gtk::DropDown::from_strings(&region_names) {
+                set_selected: Region::ALL.iter().position(|r| *r == model.region).unwrap_or(0) as u32,
+                connect_selected_notify[sender] => move |dropdown| {
+                    sender.input(Input::SelectRegion(dropdown.selected()))
+                },
+            },
+            attach[0, 1, 1, 1]= &gtk::Label {
+                set_label: "Band:",
+            },
+            #[local_ref]
+            attach[1, 1, 1, 1]= band_dropdown -> gtk::DropDown {
+                connect_selected_notify[sender] => move |dropdown| {
+                    sender.input(Input::SelectBand(dropdown.selected()))
+                },
+            },
+            attach[2, 1, 1, 1]= &gtk::Button {
+                set_label: "Remove",
+                set_tooltip_text: Some("Remove the selected custom band"),
+                connect_clicked => Input::RemoveBand,
+            },
+            attach[0, 2, 1, 1]= &gtk::Label {
+                set_label: "Custom band:",
+            },
+            attach[1, 2, 1, 1]= &gtk::Entry {
+                set_buffer: &model.band_name,
+                set_placeholder_text: Some("Name"),
+            },
+            attach[2, 2, 1, 1]= &gtk::Button {
+                set_label: "Save",
+                set_tooltip_text: Some("Save the start and stop frequency as a band"),
+                connect_clicked => Input::SaveBand,
+            },
+            attach[0, 3, 1, 1]= &gtk::Label {
+                #[watch]
+                set_label: if model.centre_span { "Centre frequency:" } else { "Start frequency:" },
             },
             #[name = "start_freq"]
-            attach[1, 0, 1, 1]= &gtk::Entry {
+            attach[1, 3, 1, 1]= &gtk::Entry {
                 set_buffer: &model.start_freq,
+                set_tooltip_text: Some("Hz, or with a k, M or G suffix, e.g. 14.2M"),
+                #[watch]
+                set_css_classes: model.classes(Field::Start),
+                #[watch]
+                set_secondary_icon_name: model.problem(Field::Start).map(|_| "dialog-warning-symbolic"),
+                #[watch]
+                set_secondary_icon_tooltip_text: model.problem(Field::Start),
+                connect_changed => Input::Validate,
             },
-            attach[0, 1, 1, 1]= &gtk::Label {
-                set_label: "Stop frequency [Hz]:",
+            attach[2, 3, 1, 1]= &gtk::CheckButton {
+                set_label: Some("Centre/span"),
+                #[watch]
+                set_active: model.centre_span,
+                connect_toggled[sender] => move |button| {
+                    sender.input(Input::SetCentreSpan(button.is_active()))
+                },
+            },
+            attach[0, 4, 1, 1]= &gtk::Label {
+                #[watch]
+                set_label: if model.centre_span { "Span:" } else { "Stop frequency:" },
             },
             #[name = "stop_freq"]
-            attach[1, 1, 1, 1]= &gtk::Entry {
+            attach[1, 4, 1, 1]= &gtk::Entry {
                 set_buffer: &model.stop_freq,
+                set_tooltip_text: Some("Hz, or with a k, M or G suffix, e.g. 14.35M"),
+                #[watch]
+                set_css_classes: model.classes(Field::Stop),
+                #[watch]
+                set_secondary_icon_name: model.problem(Field::Stop).map(|_| "dialog-warning-symbolic"),
+                #[watch]
+                set_secondary_icon_tooltip_text: model.problem(Field::Stop),
+                connect_changed => Input::Validate,
             },
-            attach[0, 2, 1, 1]= &gtk::Label {
+            attach[0, 5, 1, 1]= &gtk::Label {
                 set_label: "Step count:",
             },
             #[name = "step_count"]
-            attach[1, 2, 1, 1]= &gtk::Entry {
+            attach[1, 5, 1, 1]= &gtk::Entry {
                 set_buffer: &model.step_count,
+                #[watch]
+                set_css_classes: model.classes(Field::StepCount),
+                #[watch]
+                set_secondary_icon_name: model.problem(Field::StepCount).map(|_| "dialog-warning-symbolic")
+                    .or(model.step_note.as_ref().map(|_| "dialog-information-symbolic")),
+                #[watch]
+                set_secondary_icon_tooltip_text: model.problem(Field::StepCount).or(model.step_note.as_deref()),
+                connect_changed => Input::Validate,
             },
-            attach[0, 3, 1, 1]= &gtk::Label {
+            attach[0, 6, 1, 1]= &gtk::Label {
                 set_label: "Step time [ms]:",
             },
             #[name = "step_time"]
-            attach[1, 3, 1, 1]= &gtk::Entry {
+            attach[1, 6, 1, 1]= &gtk::Entry {
                 set_buffer: &model.step_millis,
+                #[watch]
+                set_css_classes: model.classes(Field::StepMillis),
+                #[watch]
+                set_secondary_icon_name: model.problem(Field::StepMillis).map(|_| "dialog-warning-symbolic"),
+                #[watch]
+                set_secondary_icon_tooltip_text: model.problem(Field::StepMillis),
+                connect_changed => Input::Validate,
+            },
+            attach[0, 7, 1, 1]= &gtk::Label {
+                set_label: "Noise filter:",
             },
-            attach[0, 4, 1, 2]= &gtk::Button {
+            attach[1, 7, 1, 1]= &gtk::Entry {
+                set_buffer: &model.noise_filter,
+                set_tooltip_text: Some("Noise filter setting of the analyzer"),
+                #[watch]
+                set_css_classes: model.classes(Field::NoiseFilter),
+                #[watch]
+                set_secondary_icon_name: model.problem(Field::NoiseFilter).map(|_| "dialog-warning-symbolic"),
+                #[watch]
+                set_secondary_icon_tooltip_text: model.problem(Field::NoiseFilter),
+                connect_changed => Input::Validate,
+            },
+            attach[0, 8, 1, 1]= &gtk::Label {
+                set_label: "Calibration:",
+            },
+            #[local_ref]
+            attach[1, 8, 1, 1]= calibration_dropdown -> gtk::DropDown {
+                connect_selected_notify[sender] => move |dropdown| {
+                    sender.input(Input::SelectCalibration(dropdown.selected()))
+                },
+            },
+            attach[1, 9, 1, 1]= &gtk::Button {
+                set_label: "Calibrate",
+                #[watch]
+                set_sensitive: matches!(model.state, State::Idle),
+                connect_clicked => Input::Calibrate,
+            },
+            attach[0, 10, 1, 2]= &gtk::Button {
                 set_label: "Stop",
                 #[watch]
                 set_sensitive: matches!(model.state, State::Busy),
                 connect_clicked[sender] => move |_| sender.output_sender().emit(Output::Cancel)
             },
-            attach[1, 4, 1, 1]= &gtk::Button {
+            attach[1, 10, 1, 1]= &gtk::Button {
                 set_label: "Continuous",
                 #[watch]
                 set_sensitive: matches!(model.state, State::Idle),
                 connect_clicked => Input::Continuous,
             },
-            attach[1, 5, 1, 1]= &gtk::Button {
+            attach[1, 11, 1, 1]= &gtk::Button {
                 set_label: "Oneshot",
                 #[watch]
                 set_sensitive: matches!(model.state, State::Idle),
                 connect_clicked => Input::Oneshot
             },
-            attach[1, 6, 2, 1]= &gtk::Button {
+            attach[1, 12, 2, 1]= &gtk::Button {
                 set_label: "Connect dummy",
                 #[watch]
                 set_visible: matches!(model.state, State::Disconnected),
-                connect_clicked[sender] => move |_| sender.output_sender().emit(Output::Connect {
-                    dummy: true
-                })
+                connect_clicked[sender] => move |_| sender.output_sender().emit(Output::ConfigureDummy)
+            },
+            attach[0, 13, 1, 1]= &gtk::Button {
+                set_label: "Refresh",
+                #[watch]
+                set_visible: matches!(model.state, State::Disconnected),
+                connect_clicked[sender] => move |_| sender.output_sender().emit(Output::Enumerate)
             },
-            attach[1, 7, 2, 1]= &gtk::Button {
-                set_label: "Connect Fox-Delta",
+            #[local_ref]
+            attach[1, 13, 1, 1]= device_dropdown -> gtk::DropDown {
                 #[watch]
                 set_visible: matches!(model.state, State::Disconnected),
-                connect_clicked[sender] => move |_| sender.output_sender().emit(Output::Connect {
-                    dummy: false
-                })
             },
-            attach[1, 8, 2, 1]= &gtk::Button {
+            attach[2, 13, 1, 1]= &gtk::Button {
+                set_label: "Connect",
+                #[watch]
+                set_visible: matches!(model.state, State::Disconnected),
+                #[watch]
+                set_sensitive: !model.devices.is_empty(),
+                connect_clicked => Input::ConnectDevice,
+            },
+            attach[1, 14, 2, 1]= &gtk::Button {
+                set_label: "Connect simulator",
+                #[watch]
+                set_visible: matches!(model.state, State::Disconnected),
+                connect_clicked[sender] => move |_| sender.output_sender().emit(Output::Connect(Connection::Simulator(Faults::default())))
+            },
+            attach[1, 15, 2, 1]= &gtk::Button {
                 set_label: "Install udev rules",
                 #[watch]
                 set_visible: matches!(model.state, State::Disconnected),
                 connect_clicked[sender] => move |_| sender.output_sender().emit(Output::Udev)
             },
-            attach[1, 6, 2, 1]= &gtk::Button {
+            attach[0, 16, 1, 1]= &gtk::Label {
+                set_label: "Serial port:",
+                #[watch]
+                set_visible: matches!(model.state, State::Disconnected),
+            },
+            attach[1, 16, 1, 1]= &gtk::Entry {
+                set_buffer: &model.serial_port,
+                #[watch]
+                set_visible: matches!(model.state, State::Disconnected),
+            },
+            attach[0, 17, 1, 1]= &gtk::Label {
+                set_label: "Baud rate:",
+                #[watch]
+                set_visible: matches!(model.state, State::Disconnected),
+            },
+            attach[1, 17, 1, 1]= &gtk::Entry {
+                set_buffer: &model.baud_rate,
+                #[watch]
+                set_visible: matches!(model.state, State::Disconnected),
+            },
+            attach[1, 18, 2, 1]= &gtk::Button {
+                set_label: "Connect serial",
+                #[watch]
+                set_visible: matches!(model.state, State::Disconnected),
+                connect_clicked => Input::ConnectSerial,
+            },
+            attach[1, 12, 2, 1]= &gtk::Button {
                 set_label: "Disconnect",
                 #[watch]
                 set_visible: matches!(model.state, State::Idle),
@@ -130,7 +346,7 @@ impl SimpleComponent for Controls {
                         sender.output(p).unwrap()
                     },
                     Err(e) => {
-                        error!("{}", e)
+                        error!("{}", e.message)
                     }
                 }
             }
@@ -140,25 +356,202 @@ impl SimpleComponent for Controls {
                         sender.output(p).unwrap()
                     },
                     Err(e) => {
-                        error!("{}", e)
+                        error!("{}", e.message)
+                    }
+                }
+            }
+            Input::Calibrate => {
+                match self.parse_sweep() {
+                    Ok((start_freq, stop_freq, step_count, step_millis, noise_filter)) => {
+                        sender.output(Output::Calibrate { start_freq, stop_freq, step_count, step_millis, noise_filter }).unwrap()
+                    }
+                    Err(e) => {
+                        error!("{}", e.message)
+                    }
+                }
+            }
+            Input::ConnectSerial => {
+                match self.baud_rate.text().parse::<u32>() {
+                    Ok(baud) => {
+                        let path = PathBuf::from(self.serial_port.text().as_str());
+                        sender.output(Output::Connect(Connection::Serial { path, baud })).unwrap()
+                    }
+                    Err(_) => error!("Error parsing baud rate"),
+                }
+            }
+            Input::ConnectDevice => {
+                let Some(device) = self.devices.get(self.device_dropdown.selected() as usize) else {
+                    return;
+                };
+                let id = device.id();
+                settings::set("device", &id.to_string());
+                sender.output(Output::Connect(Connection::FoxDelta(Some(id)))).unwrap()
+            }
+            Input::Devices(devices) => {
+                let names: Vec<String> = devices.iter().map(ToString::to_string).collect();
+                let names: Vec<&str> = names.iter().map(String::as_str).collect();
+                self.device_names.splice(0, self.device_names.n_items(), &names);
+                let remembered = settings::get("device").map(|id| id.parse::<DeviceId>().unwrap());
+                let index = devices.iter()
+                    .position(|device| Some(device.id()) == remembered)
+                    .unwrap_or(0);
+                self.devices = devices;
+                self.device_dropdown.set_selected(index as u32);
+            }
+            Input::SelectCalibration(index) => {
+                let file = index.checked_sub(1)
+                    .and_then(|i| self.calibration_files.get(i as usize))
+                    .cloned();
+                if file != self.calibration {
+                    self.calibration = file.clone();
+                    sender.output(Output::LoadCalibration(file)).unwrap()
+                }
+            }
+            Input::CalibrationSaved(path) => {
+                self.calibration = Some(path.clone());
+                let index = match self.calibration_files.iter().position(|f| *f == path) {
+                    Some(index) => index,
+                    None => {
+                        self.calibrations.append(&calibration_name(&path));
+                        self.calibration_files.push(path);
+                        self.calibration_files.len() - 1
+                    }
+                };
+                self.calibration_dropdown.set_selected(index as u32 + 1);
+            }
+            Input::Restore(entries) => {
+                // Sessions hold the start and stop frequency
+                self.centre_span = false;
+                for (name, value) in entries {
+                    match self.entry(&name) {
+                        Some(buffer) => buffer.set_text(value),
+                        None => error!("unknown entry '{}' in session", name),
+                    }
+                }
+            }
+            Input::SetCentreSpan(centre_span) => {
+                if centre_span == self.centre_span {
+                    return;
+                }
+                self.centre_span = centre_span;
+                if let Some((start_freq, stop_freq)) = self.range {
+                    self.set_range(start_freq, stop_freq);
+                }
+            }
+            Input::Validate => {}
+            Input::SelectRegion(index) => {
+                self.region = Region::ALL.get(index as usize).copied().unwrap_or_default();
+                settings::set("band_region", &self.region.key());
+                self.update_bands(&sender);
+            }
+            Input::SelectBand(index) => {
+                // The first entry is the placeholder
+                let Some(band) = index.checked_sub(1).and_then(|i| self.bands().into_iter().nth(i as usize)) else {
+                    return;
+                };
+                self.set_range(band.start_freq, band.stop_freq);
+            }
+            Input::SaveBand => {
+                // Commas and colons separate the bands in the settings file
+                let name = self.band_name.text().replace([',', ':'], " ").trim().to_string();
+                if name.is_empty() {
+                    error!("Enter a name for the band");
+                    return;
+                }
+                let (start_freq, stop_freq) = match self.parse_range() {
+                    Ok(range) => range,
+                    Err(e) => {
+                        error!("{}", e.message);
+                        return;
                     }
+                };
+                self.custom_bands.retain(|band| band.name != name);
+                self.custom_bands.push(Band { name, start_freq, stop_freq });
+                settings::set("custom_bands", &format_custom(&self.custom_bands));
+                self.band_name.set_text("");
+                self.update_bands(&sender);
+            }
+            Input::RemoveBand => {
+                let amateur = amateur_bands(self.region).len();
+                let Some(index) = (self.band_dropdown.selected() as usize).checked_sub(amateur + 1) else {
+                    error!("Only custom bands can be removed");
+                    return;
+                };
+                if index < self.custom_bands.len() {
+                    self.custom_bands.remove(index);
+                    settings::set("custom_bands", &format_custom(&self.custom_bands));
+                    self.update_bands(&sender);
                 }
             }
+            Input::SweepRange { start_freq, stop_freq } => {
+                if self.state != State::Idle {
+                    error!("analyzer not ready to sweep");
+                    return;
+                }
+                self.set_range(start_freq, stop_freq);
+                sender.input(Input::Oneshot);
+            }
             Input::StateChange(state) =>  {
                 self.state = state
             }
         }
+        self.range = self.parse_range().ok();
+        self.invalid = self.parse_sweep().err();
+        self.step_note = self.step_note();
     }
 
     fn init(_init: Self::Init, root: Self::Root, sender: ComponentSender<Self>) -> ComponentParts<Self> {
+        let calibration_files = Calibration::list(&calibration_dir()).unwrap_or_else(|e| {
+            error!("listing calibrations: {}", e);
+            vec![]
+        });
+        let device_names = gtk::StringList::new(&[]);
+        let calibrations = gtk::StringList::new(&["None"]);
+        for file in &calibration_files {
+            calibrations.append(&calibration_name(file));
+        }
+
+        let region = settings::get("band_region")
+            .and_then(|region| region.parse().ok())
+            .unwrap_or_default();
+        let custom_bands = settings::get("custom_bands")
+            .map(|bands| parse_custom(&bands))
+            .unwrap_or_default();
+        let band_names = gtk::StringList::new(&[]);
+
         let model = Self {
-            start_freq: gtk::EntryBuffer::new(Some("1000000")),
-            stop_freq: gtk::EntryBuffer::new(Some("35000000")),
+            start_freq: gtk::EntryBuffer::new(Some("1M")),
+            stop_freq: gtk::EntryBuffer::new(Some("35M")),
+            centre_span: false,
+            range: None,
+            invalid: None,
+            step_note: None,
             step_count: gtk::EntryBuffer::new(Some("100")),
             step_millis: gtk::EntryBuffer::new(Some("10")),
+            noise_filter: gtk::EntryBuffer::new(Some(DEFAULT_NOISE_FILTER.to_string())),
+            serial_port: gtk::EntryBuffer::new(Some("/dev/ttyUSB0")),
+            baud_rate: gtk::EntryBuffer::new(Some("115200")),
+            region,
+            custom_bands,
+            band_dropdown: gtk::DropDown::new(Some(band_names.clone()), None::<gtk::Expression>),
+            band_names,
+            band_name: gtk::EntryBuffer::new(None::<&str>),
+            device_dropdown: gtk::DropDown::new(Some(device_names.clone()), None::<gtk::Expression>),
+            device_names,
+            devices: vec![],
+            calibration_dropdown: gtk::DropDown::new(Some(calibrations.clone()), None::<gtk::Expression>),
+            calibrations,
+            calibration_files,
+            calibration: None,
             state: State::Disconnected,
         };
+        let calibration_dropdown = &model.calibration_dropdown;
+        let device_dropdown = &model.device_dropdown;
+        let band_dropdown = &model.band_dropdown;
+        let region_names: Vec<String> = Region::ALL.iter().map(ToString::to_string).collect();
+        let region_names: Vec<&str> = region_names.iter().map(String::as_str).collect();
         let widgets = view_output!();
+        model.update_bands(&sender);
         
         STATE.subscribe(sender.input_sender(), |state| Input::StateChange(*state));
 
@@ -167,17 +560,163 @@ impl SimpleComponent for Controls {
 }
 
 impl Controls {
-    fn parse_parameters(&self, continuous: bool) -> Result<Output, &str> {
-        let start_freq = self.start_freq.text().parse::<i32>().or(Err("Error parsing start frequency"))?;
-        let stop_freq = self.stop_freq.text().parse::<i32>().or(Err("Error parsing stop frequency"))?;
-        let step_count = self.step_count.text().parse::<i32>().or(Err("Error parsing step count"))?;
-        let step_millis = self.step_millis.text().parse::<i32>().or(Err("Error parsing step time"))?;
+    const ENTRIES: [&'static str; 5] = ["start_freq", "stop_freq", "step_count", "step_millis", "noise_filter"];
+
+    /// Text of every sweep parameter entry, for saving in a session.
+    pub(super) fn entries(&self) -> Vec<(String, String)> {
+        let range = self.range.filter(|_| self.centre_span);
+        Self::ENTRIES.iter()
+            .filter_map(|name| {
+                let text = match (*name, range) {
+                    ("start_freq", Some((start_freq, _))) => frequency::format(start_freq),
+                    ("stop_freq", Some((_, stop_freq))) => frequency::format(stop_freq),
+                    _ => self.entry(name)?.text().to_string(),
+                };
+                Some((name.to_string(), text))
+            })
+            .collect()
+    }
+
+    /// Amateur bands of the chosen region followed by the custom ones.
+    fn bands(&self) -> Vec<Band> {
+        let mut bands = amateur_bands(self.region);
+        bands.extend(self.custom_bands.iter().cloned());
+        bands
+    }
+
+    /// Refill the band list and pass the bands on to the graph.
+    fn update_bands(&self, sender: &ComponentSender<Self>) {
+        let bands = self.bands();
+        let names: Vec<String> = bands.iter()
+            .map(|band| format!("{} ({:.3}–{:.3} MHz)", band.name, band.start_freq as f32 / 1e6, band.stop_freq as f32 / 1e6))
+            .collect();
+        let names: Vec<&str> = std::iter::once("Select…").chain(names.iter().map(String::as_str)).collect();
+        self.band_names.splice(0, self.band_names.n_items(), &names);
+        self.band_dropdown.set_selected(0);
+        sender.output(Output::Bands(bands)).unwrap();
+    }
+
+    fn entry(&self, name: &str) -> Option<&gtk::EntryBuffer> {
+        match name {
+            "start_freq" => Some(&self.start_freq),
+            "stop_freq" => Some(&self.stop_freq),
+            "step_count" => Some(&self.step_count),
+            "step_millis" => Some(&self.step_millis),
+            "noise_filter" => Some(&self.noise_filter),
+            _ => None,
+        }
+    }
+
+    fn parse_parameters(&self, continuous: bool) -> Result<Output, Invalid> {
+        let (start_freq, stop_freq, step_count, step_millis, noise_filter) = self.parse_sweep()?;
         Ok(Output::Start {
             continuous,
             start_freq,
             stop_freq,
             step_count,
             step_millis,
+            noise_filter,
         })
     }
-}
\ No newline at end of file
+
+    /// Start and stop frequency, checked against the range of the analyzer.
+    fn parse_range(&self) -> Result<(i32, i32), Invalid> {
+        let start = frequency::parse(&self.start_freq.text()).map_err(|e| Invalid::new(Field::Start, e))?;
+        let stop = frequency::parse(&self.stop_freq.text()).map_err(|e| Invalid::new(Field::Stop, e))?;
+        let (start_freq, stop_freq) = match self.centre_span {
+            true => frequency::from_centre_span(start, stop),
+            false => (start, stop),
+        };
+        if stop_freq <= start_freq {
+            let message = match self.centre_span {
+                true => "Span must be above zero",
+                false => "Stop frequency must be above the start frequency",
+            };
+            return Err(Invalid::new(Field::Stop, message.to_string()));
+        }
+        let limits = format!("{}–{}", frequency::format(*FREQ_RANGE.start()), frequency::format(*FREQ_RANGE.end()));
+        if !FREQ_RANGE.contains(&start_freq) {
+            return Err(Invalid::new(Field::Start, format!("Start frequency is outside the {} the analyzer covers", limits)));
+        }
+        if !FREQ_RANGE.contains(&stop_freq) {
+            return Err(Invalid::new(Field::Stop, format!("Stop frequency is outside the {} the analyzer covers", limits)));
+        }
+        Ok((start_freq, stop_freq))
+    }
+
+    fn parse_sweep(&self) -> Result<(i32, i32, i32, i32, i32), Invalid> {
+        let (start_freq, stop_freq) = self.parse_range()?;
+        let step_count = self.step_count.text().trim().parse::<i32>().ok()
+            .filter(|count| *count > 0)
+            .ok_or(Invalid::new(Field::StepCount, "Step count must be a whole number above zero".to_string()))?;
+        let step_millis = self.step_millis.text().trim().parse::<i32>().ok()
+            .filter(|millis| *millis >= 0)
+            .ok_or(Invalid::new(Field::StepMillis, "Step time must be a whole number of milliseconds".to_string()))?;
+        let noise_filter = self.noise_filter.text().trim().parse::<i32>().ok()
+            .filter(|filter| *filter >= 0)
+            .ok_or(Invalid::new(Field::NoiseFilter, "Noise filter must be a whole number of zero or more".to_string()))?;
+        let plan = SweepPlan::new(start_freq, stop_freq, step_count, step_millis, noise_filter);
+        plan.check().map_err(|e| Invalid::new(Field::StepCount, e.to_string()))?;
+        // Calibrations are matched against the steps swept, not the ones entered
+        Ok((start_freq, stop_freq, plan.step_count(), step_millis, noise_filter))
+    }
+
+    fn step_note(&self) -> Option<String> {
+        let (start_freq, stop_freq, step_count, ..) = self.parse_sweep().ok()?;
+        let entered = self.step_count.text().trim().parse::<i32>().ok()?;
+        (step_count != entered).then(|| format!(
+            "Sweeping {} steps of {} Hz, the nearest count that ends on the stop frequency",
+            step_count,
+            (stop_freq - start_freq) / step_count,
+        ))
+    }
+
+    /// Show a frequency range in the entries, as start and stop or as centre and span.
+    fn set_range(&self, start_freq: i32, stop_freq: i32) {
+        let (first, second) = match self.centre_span {
+            true => frequency::to_centre_span(start_freq, stop_freq),
+            false => (start_freq, stop_freq),
+        };
+        self.start_freq.set_text(frequency::format(first));
+        self.stop_freq.set_text(frequency::format(second));
+    }
+
+    fn problem(&self, field: Field) -> Option<&str> {
+        self.invalid.as_ref()
+            .filter(|invalid| invalid.field == field)
+            .map(|invalid| invalid.message.as_str())
+    }
+
+    fn classes(&self, field: Field) -> &'static [&'static str] {
+        match self.problem(field) {
+            Some(_) => &["error"],
+            None => &[],
+        }
+    }
+}
+
+/// Sweep entry a problem is shown on.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Field {
+    Start,
+    Stop,
+    StepCount,
+    StepMillis,
+    NoiseFilter,
+}
+
+#[derive(Debug)]
+struct Invalid {
+    field: Field,
+    message: String,
+}
+
+impl Invalid {
+    fn new(field: Field, message: String) -> Self {
+        Self { field, message }
+    }
+}
+
+fn calibration_name(path: &Path) -> String {
+    path.file_stem().unwrap_or_default().to_string_lossy().into_owned()
+}
@@ -0,0 +1,221 @@
+use gtk4::glib::Propagation;
+use log::error;
+use relm4::prelude::*;
+use relm4::prelude::gtk::prelude::*;
+
+use crate::protocol::dummy::{AntennaModel, LoadKind};
+
+/// Asks for the antenna the dummy device should pretend to be connected to.
+pub(super) struct DummyDialog {
+    visible: bool,
+    kind: LoadKind,
+    resonance: gtk::EntryBuffer,
+    q: gtk::EntryBuffer,
+    resistance: gtk::EntryBuffer,
+    feedline_length: gtk::EntryBuffer,
+    feedline_impedance: gtk::EntryBuffer,
+    velocity_factor: gtk::EntryBuffer,
+    feedline_loss: gtk::EntryBuffer,
+    noise: gtk::EntryBuffer,
+}
+
+#[derive(Debug)]
+pub(super) enum Input {
+    Open,
+    SelectKind(u32),
+    Connect,
+    Close,
+}
+
+#[derive(Debug)]
+pub(super) enum Output {
+    Connect(AntennaModel),
+}
+
+#[relm4::component(pub(super))]
+//noinspection RsSortImplTraitMembers
+impl SimpleComponent for DummyDialog {
+    type Input = Input;
+    type Output = Output;
+    type Init = gtk::Window;
+
+    view! {
+        gtk::Window {
+            set_title: Some("Dummy antenna"),
+            set_modal: true,
+            set_transient_for: Some(&window),
+            #[watch]
+            set_visible: model.visible,
+
+            gtk::Box {
+                set_orientation: gtk::Orientation::Vertical,
+                set_margin_all: 10,
+                set_spacing: 10,
+
+                gtk::Grid {
+                    set_row_spacing: 5,
+                    set_column_spacing: 10,
+
+                    attach[0, 0, 1, 1]= &gtk::Label {
+                        set_label: "Model:",
+                        set_halign: gtk::Align::Start,
+                    },
+                    attach[1, 0, 1, 1]= &gtk::DropDown::from_strings(&kind_names) {
+                        connect_selected_notify[sender] => move |dropdown| {
+                            sender.input(Input::SelectKind(dropdown.selected()))
+                        },
+                    },
+                    attach[0, 1, 1, 1]= &gtk::Label {
+                        set_label: "Resonance [Hz]:",
+                        set_halign: gtk::Align::Start,
+                    },
+                    attach[1, 1, 1, 1]= &gtk::Entry {
+                        set_buffer: &model.resonance,
+                    },
+                    attach[0, 2, 1, 1]= &gtk::Label {
+                        set_label: "Q:",
+                        set_halign: gtk::Align::Start,
+                    },
+                    attach[1, 2, 1, 1]= &gtk::Entry {
+                        set_buffer: &model.q,
+                    },
+                    attach[0, 3, 1, 1]= &gtk::Label {
+                        set_label: "Resistance [Ω]:",
+                        set_halign: gtk::Align::Start,
+                    },
+                    attach[1, 3, 1, 1]= &gtk::Entry {
+                        set_buffer: &model.resistance,
+                        #[watch]
+                        set_sensitive: model.kind == LoadKind::SeriesRlc,
+                    },
+                    attach[0, 4, 1, 1]= &gtk::Label {
+                        set_label: "Feedline length [m]:",
+                        set_halign: gtk::Align::Start,
+                    },
+                    attach[1, 4, 1, 1]= &gtk::Entry {
+                        set_buffer: &model.feedline_length,
+                    },
+                    attach[0, 5, 1, 1]= &gtk::Label {
+                        set_label: "Feedline impedance [Ω]:",
+                        set_halign: gtk::Align::Start,
+                    },
+                    attach[1, 5, 1, 1]= &gtk::Entry {
+                        set_buffer: &model.feedline_impedance,
+                    },
+                    attach[0, 6, 1, 1]= &gtk::Label {
+                        set_label: "Velocity factor:",
+                        set_halign: gtk::Align::Start,
+                    },
+                    attach[1, 6, 1, 1]= &gtk::Entry {
+                        set_buffer: &model.velocity_factor,
+                    },
+                    attach[0, 7, 1, 1]= &gtk::Label {
+                        set_label: "Feedline loss at 10 MHz [dB/100 m]:",
+                        set_halign: gtk::Align::Start,
+                    },
+                    attach[1, 7, 1, 1]= &gtk::Entry {
+                        set_buffer: &model.feedline_loss,
+                    },
+                    attach[0, 8, 1, 1]= &gtk::Label {
+                        set_label: "Noise [ADC counts]:",
+                        set_halign: gtk::Align::Start,
+                    },
+                    attach[1, 8, 1, 1]= &gtk::Entry {
+                        set_buffer: &model.noise,
+                    },
+                },
+                gtk::Box {
+                    set_orientation: gtk::Orientation::Horizontal,
+                    set_halign: gtk::Align::End,
+                    set_spacing: 5,
+
+                    gtk::Button {
+                        set_label: "Cancel",
+                        connect_clicked => Input::Close,
+                    },
+                    gtk::Button {
+                        set_label: "Connect",
+                        connect_clicked => Input::Connect,
+                    },
+                },
+            },
+
+            connect_close_request[sender] => move |_| {
+                sender.input(Input::Close);
+                Propagation::Stop
+            }
+        }
+    }
+
+    fn init(window: Self::Init, root: Self::Root, sender: ComponentSender<Self>) -> ComponentParts<Self> {
+        let defaults = AntennaModel::default();
+        let buffer = |value: f32| gtk::EntryBuffer::new(Some(value.to_string()));
+        let model = Self {
+            visible: false,
+            kind: defaults.kind,
+            resonance: buffer(defaults.resonance),
+            q: buffer(defaults.q),
+            resistance: buffer(defaults.resistance),
+            feedline_length: buffer(defaults.feedline_length),
+            feedline_impedance: buffer(defaults.feedline_impedance),
+            velocity_factor: buffer(defaults.velocity_factor),
+            feedline_loss: buffer(defaults.feedline_loss),
+            noise: buffer(defaults.noise),
+        };
+
+        let kind_names: Vec<String> = LoadKind::ALL.iter().map(ToString::to_string).collect();
+        let kind_names: Vec<&str> = kind_names.iter().map(String::as_str).collect();
+        let widgets = view_output!();
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>) {
+        match message {
+            Input::Open => {
+                self.visible = true;
+            }
+            Input::SelectKind(index) => {
+                self.kind = LoadKind::ALL.get(index as usize).copied().unwrap_or_default();
+            }
+            Input::Connect => {
+                match self.parse_model() {
+                    Ok(model) => {
+                        sender.output(Output::Connect(model)).unwrap();
+                        self.visible = false;
+                    }
+                    Err(e) => error!("{}", e),
+                }
+            }
+            Input::Close => {
+                self.visible = false;
+            }
+        }
+    }
+}
+
+impl DummyDialog {
+    fn parse_model(&self) -> Result<AntennaModel, String> {
+        let parse = |buffer: &gtk::EntryBuffer, name: &str| {
+            buffer.text().parse::<f32>()
+                .ok()
+                .filter(|value| value.is_finite())
+                .ok_or(format!("Error parsing {}", name))
+        };
+        let model = AntennaModel {
+            kind: self.kind,
+            resonance: parse(&self.resonance, "resonance")?,
+            q: parse(&self.q, "Q")?,
+            resistance: parse(&self.resistance, "resistance")?,
+            feedline_length: parse(&self.feedline_length, "feedline length")?,
+            feedline_impedance: parse(&self.feedline_impedance, "feedline impedance")?,
+            velocity_factor: parse(&self.velocity_factor, "velocity factor")?,
+            feedline_loss: parse(&self.feedline_loss, "feedline loss")?,
+            noise: parse(&self.noise, "noise")?,
+        };
+        if model.resonance <= 0.0 || model.feedline_impedance <= 0.0 || model.velocity_factor <= 0.0 {
+            return Err("Resonance, feedline impedance and velocity factor must be positive".to_string());
+        }
+        Ok(model)
+    }
+}
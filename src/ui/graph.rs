@@ -1,35 +1,134 @@
 use std::cmp::Ordering;
-use gtk4::{EventControllerMotion, hsv_to_rgb, MultiSelection, ResponseType};
-use gtk4::gdk::RGBA;
-use log::{debug, error, warn};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use gtk4::{EventControllerMotion, EventControllerScroll, EventControllerScrollFlags, GestureDrag, MultiSelection, ResponseType};
+use gtk4::gdk::{ModifierType, RGBA};
+use log::{debug, error, info, warn};
 use plotters::coord::ReverseCoordTranslate;
+use plotters::coord::types::RangedCoordf32;
 use plotters::prelude::*;
 use plotters_cairo::CairoBackend;
-use rand::{Rng, thread_rng};
 use relm4::abstractions::DrawHandler;
-use relm4::binding::{Binding, BoolBinding, F32Binding};
+use relm4::binding::Binding;
 use relm4::prelude::*;
 use relm4::prelude::gtk::prelude::*;
 use relm4::typed_view::column::TypedColumnView;
 
-use crate::ui::graph::color_binding::RGBABinding;
-use crate::ui::graph::element::GraphElement;
+use crate::bands::Band;
+use crate::format::{csv, touchstone};
+use crate::format::session::{Extents, SessionTrace};
+use crate::protocol::measurement::Reflection;
+use crate::protocol::SweepParams;
+use crate::ui::graph::accumulate::{Accumulation, Accumulator, DEFAULT_DEPTH};
+use crate::ui::graph::analysis::{NARROW_SWR, WIDE_SWR};
+use crate::ui::graph::element::{find, GraphElement};
+use crate::ui::graph::marker::Marker;
+use crate::ui::graph::math::{Derivation, Operation};
+use crate::ui::graph::mode::GraphMode;
+use crate::ui::graph::scale::Scale;
 use crate::ui::swr_worker::Sample;
+use crate::ui::util::choose_file;
 
+mod accumulate;
+mod analysis;
 mod element;
 mod color_binding;
+mod marker;
+mod math;
+mod mode;
+mod polar;
+mod scale;
 
 pub struct Graph {
     x_min: f32,
     x_max: f32,
     y_min: f32,
     y_max: f32,
+    /// Axes to go back to when the view is reset, following the sweep range and graph mode
+    home: Extents,
+    drag: Option<Drag>,
+    /// Frequency span picked by shift-dragging, offered for sweeping
+    selection: Option<(f32, f32)>,
+    mode: GraphMode,
+    scale: Scale,
+    /// Draw SWR on a logarithmic axis
+    log_scale: bool,
+    /// Limits used by the manual scale, in the plotted quantity
+    manual_min: gtk::EntryBuffer,
+    manual_max: gtk::EntryBuffer,
+    view: View,
+    /// How the trace being measured averages repeated sweeps
+    accumulation: Accumulation,
+    /// Sweeps averaged over
+    average_depth: usize,
+    max_hold: bool,
+    min_hold: bool,
+    /// Ids of the hold traces following the trace being measured
+    holds: Vec<(Accumulation, usize)>,
+    /// Bands shaded behind the traces
+    bands: Vec<Band>,
+    show_bands: bool,
     active: Option<u32>,
     elements: TypedColumnView<GraphElement, MultiSelection>,
+    markers: TypedColumnView<Marker, MultiSelection>,
+    marker_count: usize,
+    /// Number of the marker the delta readouts are relative to
+    reference: Option<usize>,
+    /// Id of the trace other traces are combined with
+    trace_reference: Option<usize>,
+    operation: Operation,
     draw_handler: DrawHandler,
+    /// Chart coordinates of the last drawn frame, for mapping clicks back onto the axes
+    coords: Option<Cartesian2d<RangedCoordf32, RangedCoordf32>>,
     pointer: Option<(f64, f64)>,
-    color_picker: Option<u32>,
+    /// Id of the trace whose color is being picked
+    color_picker: Option<usize>,
     last_color: Option<RGBA>,
+    window: gtk::Window,
+    sweep_count: usize,
+    /// A frame is already due to draw the latest changes
+    frame_pending: bool,
+}
+
+/// How the traces are plotted.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum View {
+    /// Selected quantity against frequency
+    #[default]
+    Cartesian,
+    /// |Γ| against frequency in polar form
+    Polar,
+}
+
+/// What dragging over the chart does, chosen by the modifiers held when it starts.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DragMode {
+    Pan,
+    /// Zoom into the dragged out box
+    Zoom,
+    /// Pick a frequency span to sweep
+    Select,
+}
+
+#[derive(Clone)]
+struct Drag {
+    mode: DragMode,
+    start: (f64, f64),
+    offset: (f64, f64),
+    /// View and chart coordinates when the drag started
+    view: Extents,
+    coords: Cartesian2d<RangedCoordf32, RangedCoordf32>,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum FileAction {
+    ImportTouchstone,
+    ExportTouchstone,
+    ImportCsv,
+    ExportCsv,
 }
 
 #[derive(Debug)]
@@ -37,15 +136,61 @@ pub enum Input {
     Clear {
         x_min: f32,
         x_max: f32,
-        y_min: f32,
-        y_max: f32,
+        params: SweepParams,
+        device: Option<String>,
     },
-    Sample(Sample),
+    Samples(Vec<Sample>),
+    SetAccumulation(Accumulation),
+    SetAverageDepth(usize),
+    /// Follow the trace being measured with a max or min hold trace
+    SetHold(Accumulation, bool),
+    /// Start averaging and holding over from the next sweep
+    ResetAccumulation,
+    /// Combine other traces with the selected one
+    SetTraceReference,
+    SetOperation(Operation),
+    /// Add a trace combining each selected trace with the reference
+    Derive,
+    SetMode(GraphMode),
+    SetScale(Scale),
+    SetLogScale(bool),
+    SetBands(Vec<Band>),
+    ShowBands(bool),
+    ApplyLimits,
+    SetView(View),
     PointerMove(Option<(f64, f64)>),
+    /// Pin a marker to the trace nearest to the clicked point
+    PlaceMarker(f64, f64),
+    SetReference(usize),
+    DeleteMarker(usize),
+    /// Zoom around the pointer, in steps of the scroll wheel
+    Scroll(f64),
+    DragBegin(f64, f64, DragMode),
+    DragUpdate(f64, f64),
+    DragEnd(f64, f64),
+    ResetView,
+    SweepSelection,
     Redraw,
-    ColorPicker(u32),
-    Delete(u32),
+    /// Draw what changed since the last frame
+    Frame,
+    ColorPicker(usize),
+    Delete(usize),
     SetColor(Option<RGBA>),
+    ChooseFile(FileAction),
+    File(FileAction, PathBuf),
+    Restore {
+        view: Extents,
+        traces: Vec<SessionTrace>,
+    },
+}
+
+#[derive(Debug)]
+pub enum Output {
+    /// Sweep the frequency span selected on the graph
+    SweepRange {
+        start_freq: f32,
+        stop_freq: f32,
+    },
 }
 
 #[relm4::component(pub)]
@@ -53,13 +198,154 @@ pub enum Input {
 impl Component for Graph {
     type CommandOutput = ();
     type Input = Input;
-    type Output = ();
+    type Output = Output;
     type Init = gtk::Window;
 
     view! {
         #[root]
         gtk::Box {
             set_orientation: gtk::Orientation::Vertical,
+            gtk::Box {
+                set_orientation: gtk::Orientation::Horizontal,
+                set_spacing: 5,
+                gtk::Box {
+                    add_css_class: "linked",
+                    #[name = "cartesian_tab"]
+                    gtk::ToggleButton {
+                        set_label: "Cartesian",
+                        set_active: true,
+                        connect_toggled[sender] => move |button| {
+                            if button.is_active() {
+                                sender.input(Input::SetView(View::Cartesian))
+                            }
+                        },
+                    },
+                    gtk::ToggleButton {
+                        set_label: "Polar",
+                        set_group: Some(&cartesian_tab),
+                        connect_toggled[sender] => move |button| {
+                            if button.is_active() {
+                                sender.input(Input::SetView(View::Polar))
+                            }
+                        },
+                    },
+                },
+                gtk::Button {
+                    set_label: "Reset view",
+                    set_tooltip_text: Some("Scroll to zoom, drag to pan, ctrl-drag to zoom into a box"),
+                    connect_clicked => Input::ResetView,
+                },
+                gtk::Button {
+                    set_label: "Sweep this range",
+                    set_tooltip_text: Some("Shift-drag across the graph to select a range"),
+                    #[watch]
+                    set_sensitive: model.selection.is_some(),
+                    connect_clicked => Input::SweepSelection,
+                },
+                gtk::Label {
+                    set_label: "Plot:",
+                },
+                gtk::DropDown::from_strings(&mode_names) {
+                    connect_selected_notify[sender] => move |dropdown| {
+                        let mode = GraphMode::ALL[dropdown.selected() as usize];
+                        sender.input(Input::SetMode(mode))
+                    },
+                },
+                gtk::Label {
+                    set_label: "Y axis:",
+                },
+                gtk::DropDown::from_strings(&scale_names) {
+                    connect_selected_notify[sender] => move |dropdown| {
+                        let scale = Scale::ALL[dropdown.selected() as usize];
+                        sender.input(Input::SetScale(scale))
+                    },
+                },
+                gtk::Entry {
+                    set_buffer: &model.manual_min,
+                    set_width_chars: 6,
+                    set_tooltip_text: Some("Minimum"),
+                    #[watch]
+                    set_sensitive: model.scale == Scale::Manual,
+                    connect_activate => Input::ApplyLimits,
+                },
+                gtk::Entry {
+                    set_buffer: &model.manual_max,
+                    set_width_chars: 6,
+                    set_tooltip_text: Some("Maximum"),
+                    #[watch]
+                    set_sensitive: model.scale == Scale::Manual,
+                    connect_activate => Input::ApplyLimits,
+                },
+                gtk::CheckButton {
+                    set_label: Some("Bands"),
+                    set_active: true,
+                    connect_toggled[sender] => move |button| {
+                        sender.input(Input::ShowBands(button.is_active()))
+                    },
+                },
+                gtk::CheckButton {
+                    set_label: Some("Log SWR"),
+                    #[watch]
+                    set_sensitive: model.mode == GraphMode::Swr && !model.derived_visible(),
+                    connect_toggled[sender] => move |button| {
+                        sender.input(Input::SetLogScale(button.is_active()))
+                    },
+                },
+                gtk::Label {
+                    set_label: "Average:",
+                },
+                gtk::DropDown::from_strings(&accumulation_names) {
+                    set_tooltip_text: Some("How repeated sweeps are averaged"),
+                    connect_selected_notify[sender] => move |dropdown| {
+                        let accumulation = Accumulation::AVERAGES[dropdown.selected() as usize];
+                        sender.input(Input::SetAccumulation(accumulation))
+                    },
+                },
+                gtk::SpinButton::with_range(2.0, 100.0, 1.0) {
+                    set_value: DEFAULT_DEPTH as f64,
+                    set_tooltip_text: Some("Sweeps to average over"),
+                    #[watch]
+                    set_sensitive: model.accumulation.averages(),
+                    connect_value_changed[sender] => move |spin| {
+                        sender.input(Input::SetAverageDepth(spin.value_as_int() as usize))
+                    },
+                },
+                gtk::CheckButton {
+                    set_label: Some("Max hold"),
+                    connect_toggled[sender] => move |button| {
+                        sender.input(Input::SetHold(Accumulation::MaxHold, button.is_active()))
+                    },
+                },
+                gtk::CheckButton {
+                    set_label: Some("Min hold"),
+                    connect_toggled[sender] => move |button| {
+                        sender.input(Input::SetHold(Accumulation::MinHold, button.is_active()))
+                    },
+                },
+                gtk::Button {
+                    set_label: "Reset",
+                    set_tooltip_text: Some("Start averaging and holding over"),
+                    #[watch]
+                    set_sensitive: model.accumulation != Accumulation::Off || model.max_hold || model.min_hold,
+                    connect_clicked => Input::ResetAccumulation,
+                },
+                gtk::Button {
+                    set_label: "Import S1P",
+                    connect_clicked => Input::ChooseFile(FileAction::ImportTouchstone),
+                },
+                gtk::Button {
+                    set_label: "Export S1P",
+                    connect_clicked => Input::ChooseFile(FileAction::ExportTouchstone),
+                },
+                gtk::Button {
+                    set_label: "Import CSV",
+                    connect_clicked => Input::ChooseFile(FileAction::ImportCsv),
+                },
+                gtk::Button {
+                    set_label: "Export CSV",
+                    connect_clicked => Input::ChooseFile(FileAction::ExportCsv),
+                },
+            },
             #[local_ref]
             drawing_area -> gtk::DrawingArea {
                 set_hexpand: true,
@@ -72,14 +358,84 @@ impl Component for Graph {
                         sender.input(Input::PointerMove(Some((x, y))))
                     },
                     connect_leave => Input::PointerMove(None),
-                }
+                },
+                add_controller= gtk::GestureClick {
+                    connect_released[sender] => move |_, _, x, y| {
+                        sender.input(Input::PlaceMarker(x, y))
+                    },
+                },
+                add_controller= EventControllerScroll {
+                    set_flags: EventControllerScrollFlags::VERTICAL,
+                    connect_scroll[sender] => move |_, _, dy| {
+                        sender.input(Input::Scroll(dy));
+                        gtk::glib::Propagation::Stop
+                    },
+                },
+                add_controller= GestureDrag {
+                    connect_drag_begin[sender] => move |gesture, x, y| {
+                        let modifiers = gesture.current_event_state();
+                        let mode = if modifiers.contains(ModifierType::SHIFT_MASK) {
+                            DragMode::Select
+                        } else if modifiers.contains(ModifierType::CONTROL_MASK) {
+                            DragMode::Zoom
+                        } else {
+                            DragMode::Pan
+                        };
+                        sender.input(Input::DragBegin(x, y, mode))
+                    },
+                    connect_drag_update[sender] => move |_, x, y| {
+                        sender.input(Input::DragUpdate(x, y))
+                    },
+                    connect_drag_end[sender] => move |_, x, y| {
+                        sender.input(Input::DragEnd(x, y))
+                    },
+                },
             },
-            gtk::ScrolledWindow {
-                set_height_request: 150,
-                #[local_ref]
-                col_view -> gtk::ColumnView {
+            gtk::Box {
+                set_orientation: gtk::Orientation::Horizontal,
+                set_spacing: 5,
+                gtk::Box {
+                    set_orientation: gtk::Orientation::Vertical,
+                    set_spacing: 5,
+                    gtk::Button {
+                        set_label: "Use as reference",
+                        set_tooltip_text: Some("Combine other traces with the selected one"),
+                        connect_clicked => Input::SetTraceReference,
+                    },
+                    gtk::Label {
+                        set_ellipsize: gtk::pango::EllipsizeMode::End,
+                        set_max_width_chars: 20,
+                        #[watch]
+                        set_label: &model.trace_reference_name(),
+                    },
+                    gtk::DropDown::from_strings(&operation_names) {
+                        set_tooltip_text: Some("A is each selected trace, B the reference"),
+                        connect_selected_notify[sender] => move |dropdown| {
+                            let operation = Operation::ALL[dropdown.selected() as usize];
+                            sender.input(Input::SetOperation(operation))
+                        },
+                    },
+                    gtk::Button {
+                        set_label: "Add trace",
+                        #[watch]
+                        set_sensitive: model.trace_reference.is_some(),
+                        connect_clicked => Input::Derive,
+                    },
+                },
+                gtk::ScrolledWindow {
+                    set_height_request: 150,
                     set_hexpand: true,
-                }
+                    #[local_ref]
+                    col_view -> gtk::ColumnView {
+                        set_hexpand: true,
+                    }
+                },
+                gtk::ScrolledWindow {
+                    set_height_request: 150,
+                    set_width_request: 450,
+                    #[local_ref]
+                    marker_view -> gtk::ColumnView {}
+                },
             },
         },
         #[name(color_picker)]
@@ -109,71 +465,278 @@ impl Component for Graph {
 
     fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>, _root: &Self::Root) {
         self.last_color = None;
+        // Fitted scales follow the traces, but not while the user is moving the view
+        let refit = matches!(message,
+            Input::Clear { .. } | Input::Samples(_) | Input::Redraw | Input::Delete(_) | Input::Restore { .. } | Input::File(..)
+            | Input::Derive);
+        let scale = self.scale();
         match message {
             Input::Clear {
                 x_min: start_freq,
                 x_max: stop_freq,
-                y_min,
-                y_max,
+                params,
+                device,
             } => {
                 self.x_min = start_freq;
                 self.x_max = stop_freq;
-                self.y_min = y_min;
-                self.y_max = y_max;
+                (self.home.x_min, self.home.x_max) = (start_freq, stop_freq);
+                self.selection = None;
                 if let Some(active) = self.active {
                     let previous_element = self.elements.get(active).unwrap();
                     let previous_element = previous_element.borrow_mut();
                     previous_element.visible.set(false);
                 }
-                let (r, g, b) = hsv_to_rgb(thread_rng().gen_range(0.0..1.0), 1.0, 1.0);
+                for (_, id) in std::mem::take(&mut self.holds) {
+                    if let Some(hold) = self.index_of(id).and_then(|index| self.elements.get(index)) {
+                        hold.borrow().visible.set(false);
+                    }
+                }
+                self.sweep_count += 1;
                 let element = GraphElement {
-                    visible: BoolBinding::new(true),
-                    x_min: F32Binding::new(start_freq),
-                    x_max: F32Binding::new(stop_freq),
-                    y_min: F32Binding::new(y_min),
-                    y_max: F32Binding::new(y_max),
-                    samples: vec![],
-                    color: RGBABinding::new(RGBA::new(r, g, b, 1.0)),
-                    sender: sender.input_sender().clone(),
+                    device,
+                    timestamp: Some(Local::now()),
+                    params: Some(params),
+                    accumulator: Accumulator::new(self.accumulation, self.average_depth),
+                    ..GraphElement::new(
+                        format!("Sweep {}", self.sweep_count),
+                        start_freq,
+                        stop_freq,
+                        sender.input_sender().clone(),
+                    )
                 };
-                element.visible.connect_value_notify(move |_| sender.input(Input::Redraw));
-                self.elements.append(element);
-                self.active = Some(self.elements.len() - 1)
+                self.append_element(element, &sender);
+                self.active = Some(self.elements.len() - 1);
+                for (hold, on) in [(Accumulation::MaxHold, self.max_hold), (Accumulation::MinHold, self.min_hold)] {
+                    if on {
+                        self.add_hold(hold, &sender);
+                    }
+                }
             }
-            Input::Sample(sample) => {
+            Input::Samples(samples) => {
                 let Some(active) = self.active else {
-                    error!("unexpected sample");
+                    error!("unexpected samples");
                     return;
                 };
                 let Some(graph) = self.elements.get(active) else {
                     panic!("graph does not exist");
                 };
+                for &(_, id) in &self.holds {
+                    if let Some(hold) = self.index_of(id).and_then(|index| self.elements.get(index)) {
+                        hold.borrow_mut().push_samples(samples.clone());
+                    }
+                }
                 let mut graph = graph.borrow_mut();
-                graph.push_sample(sample);
+                graph.push_samples(samples);
+            }
+            Input::SetAccumulation(accumulation) => {
+                self.accumulation = accumulation;
+                self.restart_accumulation();
+            }
+            Input::SetAverageDepth(depth) => {
+                self.average_depth = depth;
+                self.restart_accumulation();
+            }
+            Input::SetHold(hold, on) => {
+                match hold {
+                    Accumulation::MaxHold => self.max_hold = on,
+                    _ => self.min_hold = on,
+                }
+                if on {
+                    self.add_hold(hold, &sender);
+                } else {
+                    // The trace stays, it just no longer follows the sweep
+                    self.holds.retain(|&(accumulation, _)| accumulation != hold);
+                }
+            }
+            Input::ResetAccumulation => {
+                if let Some(element) = self.active.and_then(|active| self.elements.get(active)) {
+                    element.borrow_mut().restart_accumulation();
+                }
+                for &(_, id) in &self.holds {
+                    if let Some(hold) = self.index_of(id).and_then(|index| self.elements.get(index)) {
+                        hold.borrow_mut().clear_samples();
+                    }
+                }
+            }
+            Input::SetTraceReference => {
+                match self.selected().as_slice() {
+                    &[index] => self.trace_reference = Some(self.elements.get(index).unwrap().borrow().id),
+                    _ => error!("select one trace to use as reference"),
+                }
+            }
+            Input::SetOperation(operation) => {
+                self.operation = operation;
+            }
+            Input::Derive => {
+                self.derive(&sender);
+            }
+            Input::SetMode(mode) => {
+                self.mode = mode;
+                if self.scale == Scale::Manual {
+                    // Limits typed for one quantity mean nothing for another
+                    let (y_min, y_max) = mode.range();
+                    self.manual_min.set_text(y_min.to_string());
+                    self.manual_max.set_text(y_max.to_string());
+                }
+                self.apply_scale();
+            }
+            Input::SetScale(scale) => {
+                self.scale = scale;
+                if scale == Scale::Manual {
+                    // Start from what is on screen
+                    self.manual_min.set_text(format!("{:.3}", self.y_min));
+                    self.manual_max.set_text(format!("{:.3}", self.y_max));
+                }
+                self.apply_scale();
+            }
+            Input::SetLogScale(log_scale) => {
+                self.log_scale = log_scale;
+                if self.scale().fits() {
+                    self.apply_scale();
+                }
+            }
+            Input::SetBands(bands) => {
+                self.bands = bands;
+            }
+            Input::ShowBands(show_bands) => {
+                self.show_bands = show_bands;
+            }
+            Input::ApplyLimits => {
+                self.apply_scale();
+            }
+            Input::Scroll(dy) => {
+                let (Some(coords), Some(pointer)) = (&self.coords, self.pointer) else {
+                    return;
+                };
+                let (x, y) = to_data(coords, pointer);
+                let factor = 1.2f32.powf(dy as f32);
+                self.x_min = x + (self.x_min - x) * factor;
+                self.x_max = x + (self.x_max - x) * factor;
+                self.y_min = self.value(y + (self.axis(self.y_min) - y) * factor);
+                self.y_max = self.value(y + (self.axis(self.y_max) - y) * factor);
+            }
+            Input::DragBegin(x, y, mode) => {
+                self.drag = self.coords.clone().map(|coords| Drag {
+                    mode,
+                    start: (x, y),
+                    offset: (0.0, 0.0),
+                    view: self.extents(),
+                    coords,
+                });
+            }
+            Input::DragUpdate(dx, dy) => {
+                let Some(drag) = &mut self.drag else {
+                    return;
+                };
+                drag.offset = (dx, dy);
+                match drag.mode {
+                    DragMode::Pan => {
+                        let drag = drag.clone();
+                        self.pan(&drag);
+                    }
+                    DragMode::Select => {
+                        let drag = drag.clone();
+                        self.select(&drag);
+                    }
+                    DragMode::Zoom => {}
+                }
+            }
+            Input::DragEnd(dx, dy) => {
+                let Some(mut drag) = self.drag.take() else {
+                    return;
+                };
+                drag.offset = (dx, dy);
+                match drag.mode {
+                    DragMode::Pan => self.pan(&drag),
+                    DragMode::Select => self.select(&drag),
+                    DragMode::Zoom => {
+                        // Ignore boxes too small to be deliberate
+                        if dx.abs() > 5.0 && dy.abs() > 5.0 {
+                            let (x1, y1) = to_data(&drag.coords, drag.start);
+                            let (x2, y2) = to_data(&drag.coords, (drag.start.0 + dx, drag.start.1 + dy));
+                            (self.x_min, self.x_max) = (x1.min(x2), x1.max(x2));
+                            (self.y_min, self.y_max) = (self.value(y1.min(y2)), self.value(y1.max(y2)));
+                        }
+                    }
+                }
+            }
+            Input::SweepSelection => {
+                if let Some((start_freq, stop_freq)) = self.selection {
+                    sender.output(Output::SweepRange { start_freq, stop_freq }).unwrap();
+                }
+            }
+            Input::ResetView => {
+                let Extents { x_min, x_max, y_min, y_max } = self.home;
+                (self.x_min, self.x_max, self.y_min, self.y_max) = (x_min, x_max, y_min, y_max);
+            }
+            Input::SetView(view) => {
+                self.view = view;
             }
             Input::Redraw => {}
+            Input::Frame => {
+                self.frame_pending = false;
+                self.update_markers();
+                self.draw();
+                return;
+            }
             Input::PointerMove(pointer) => {
                 self.pointer = pointer;
             }
+            Input::PlaceMarker(x, y) => {
+                let Some((id, (freq, _))) = self.coords.as_ref()
+                    .and_then(|coords| coords.reverse_translate((x as i32, y as i32)))
+                    .and_then(|p| self.get_closest(p)) else {
+                    return;
+                };
+                let Some(index) = self.index_of(id) else {
+                    return;
+                };
+                let trace = self.elements.get(index).unwrap().borrow().name.clone();
+                self.marker_count += 1;
+                let marker = Marker::new(self.marker_count, id, trace, freq, sender.input_sender().clone());
+                self.markers.append(marker);
+            }
+            Input::SetReference(number) => {
+                self.reference = (self.reference != Some(number)).then_some(number);
+            }
+            Input::DeleteMarker(number) => {
+                if let Some(index) = find(&self.markers, |m| m.number == number) {
+                    self.markers.remove(index);
+                }
+                if self.reference == Some(number) {
+                    self.reference = None;
+                }
+            }
 
-            Input::ColorPicker(index) => {
-                println!("color picker {}", index);
+            Input::ColorPicker(id) => {
+                let Some(index) = self.index_of(id) else {
+                    return;
+                };
                 let color = self.elements.get(index).unwrap().borrow().color.get();
-                self.color_picker = Some(index);
+                self.color_picker = Some(id);
                 self.last_color = Some(color);
             }
             Input::SetColor(color) => {
-                if let Some(i) = self.color_picker.take() {
-                    if let Some(color) = color {
-                        let elem = self.elements.get(i).unwrap();
-                        elem.borrow_mut().color.set(color);
+                match self.color_picker.take().and_then(|id| self.index_of(id)) {
+                    Some(index) => {
+                        if let Some(color) = color {
+                            let elem = self.elements.get(index).unwrap();
+                            elem.borrow_mut().color.set(color);
+                        }
                     }
-                } else {
-                    warn!("color picked for unknown element");
+                    None => warn!("color picked for unknown element"),
                 }
             }
-            Input::Delete(index) => {
+            Input::Delete(id) => {
+                let Some(index) = self.index_of(id) else {
+                    return;
+                };
+                self.remove_markers(id);
                 self.elements.remove(index);
+                self.holds.retain(|&(_, hold)| hold != id);
+                if self.trace_reference == Some(id) {
+                    self.trace_reference = None;
+                }
                 if let Some(prev) = self.active.take() {
                     match prev.cmp(&index) {
                         Ordering::Less => {
@@ -188,26 +751,127 @@ impl Component for Graph {
                     }
                 }
             }
+            Input::Restore { view, traces } => {
+                let Extents { x_min, x_max, y_min, y_max } = view;
+                (self.x_min, self.x_max, self.y_min, self.y_max) = (x_min, x_max, y_min, y_max);
+                self.home = view;
+                self.elements.clear();
+                self.markers.clear();
+                self.reference = None;
+                self.trace_reference = None;
+                self.active = None;
+                self.holds.clear();
+                for trace in traces {
+                    let element = GraphElement::from_session(trace, sender.input_sender().clone());
+                    self.append_element(element, &sender);
+                }
+            }
+            Input::ChooseFile(action) => {
+                let (title, chooser_action, filter) = match action {
+                    FileAction::ImportTouchstone => ("Import Touchstone", gtk::FileChooserAction::Open, ("Touchstone (*.s1p)", "*.s1p")),
+                    FileAction::ExportTouchstone => ("Export Touchstone", gtk::FileChooserAction::Save, ("Touchstone (*.s1p)", "*.s1p")),
+                    FileAction::ImportCsv => ("Import CSV", gtk::FileChooserAction::Open, ("CSV (*.csv)", "*.csv")),
+                    FileAction::ExportCsv => ("Export CSV", gtk::FileChooserAction::Save, ("CSV (*.csv)", "*.csv")),
+                };
+                let sender = sender.clone();
+                choose_file(&self.window, title, chooser_action, filter, move |path| {
+                    sender.input(Input::File(action, path))
+                });
+            }
+            Input::File(FileAction::ImportTouchstone, path) => {
+                match File::open(&path).map_err(Into::into).and_then(|f| touchstone::read(BufReader::new(f))) {
+                    Ok(samples) => {
+                        let name = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+                        let element = GraphElement::from_samples(name, samples, sender.input_sender().clone());
+                        self.append_element(element, &sender);
+                    }
+                    Err(e) => error!("importing {}: {}", path.display(), e),
+                }
+            }
+            Input::File(FileAction::ExportTouchstone, path) => {
+                self.export_touchstone(&path);
+            }
+            Input::File(FileAction::ImportCsv, path) => {
+                match File::open(&path).map_err(Into::into).and_then(|f| csv::read(BufReader::new(f))) {
+                    Ok(traces) => {
+                        for trace in traces {
+                            let element = GraphElement::from_trace(trace, sender.input_sender().clone());
+                            self.append_element(element, &sender);
+                        }
+                    }
+                    Err(e) => error!("importing {}: {}", path.display(), e),
+                }
+            }
+            Input::File(FileAction::ExportCsv, path) => {
+                let traces: Vec<_> = self.exportable().into_iter()
+                    .map(|i| self.elements.get(i).unwrap().borrow().to_trace())
+                    .collect();
+                let result = File::create(&path).and_then(|f| csv::write(BufWriter::new(f), &traces));
+                match result {
+                    Ok(()) => info!("exported {} traces to {}", traces.len(), path.display()),
+                    Err(e) => error!("exporting {}: {}", path.display(), e),
+                }
+            }
         };
-        self.draw();
+        // Showing or hiding derived traces can switch between the default and a fitted scale
+        if refit && (self.scale().fits() || self.scale() != scale) {
+            self.apply_scale();
+        }
+        self.queue_frame(&sender);
     }
 
     fn init(window: Self::Init, root: Self::Root, sender: ComponentSender<Self>) -> ComponentParts<Self> {
+        let mode = GraphMode::default();
+        let (y_min, y_max) = mode.range();
         let model = Self {
             x_min: 0.0,
             x_max: 1000000.0,
-            y_min: 0.0,
-            y_max: 1.0,
+            y_min,
+            y_max,
+            home: Extents { x_min: 0.0, x_max: 1000000.0, y_min, y_max },
+            drag: None,
+            selection: None,
+            mode,
+            scale: Scale::default(),
+            log_scale: false,
+            manual_min: gtk::EntryBuffer::new(Some(y_min.to_string())),
+            manual_max: gtk::EntryBuffer::new(Some(y_max.to_string())),
+            view: View::default(),
+            accumulation: Accumulation::default(),
+            average_depth: DEFAULT_DEPTH,
+            max_hold: false,
+            min_hold: false,
+            holds: vec![],
+            bands: vec![],
+            show_bands: true,
             active: None,
             elements: GraphElement::column_view(),
+            markers: Marker::column_view(),
+            marker_count: 0,
+            reference: None,
+            trace_reference: None,
+            operation: Operation::default(),
             draw_handler: DrawHandler::new(),
+            coords: None,
             pointer: None,
             color_picker: None,
             last_color: None,
+            window: window.clone(),
+            sweep_count: 0,
+            frame_pending: false,
         };
 
         let drawing_area = model.draw_handler.drawing_area();
         let col_view = &model.elements.view;
+        let marker_view = &model.markers.view;
+        let mode_names: Vec<String> = GraphMode::ALL.iter().map(ToString::to_string).collect();
+        let mode_names: Vec<&str> = mode_names.iter().map(String::as_str).collect();
+        let scale_names: Vec<String> = Scale::ALL.iter().map(ToString::to_string).collect();
+        let scale_names: Vec<&str> = scale_names.iter().map(String::as_str).collect();
+        let accumulation_names: Vec<String> = Accumulation::AVERAGES.iter().map(ToString::to_string).collect();
+        let accumulation_names: Vec<&str> = accumulation_names.iter().map(String::as_str).collect();
+        let operation_names: Vec<String> = Operation::ALL.iter().map(ToString::to_string).collect();
+        let operation_names: Vec<&str> = operation_names.iter().map(String::as_str).collect();
 
         let widgets = view_output!();
 
@@ -219,7 +883,189 @@ impl Component for Graph {
 }
 
 impl Graph {
+    /// Current view and all traces, for saving in a session.
+    pub(super) fn session(&self) -> (Extents, Vec<SessionTrace>) {
+        let view = Extents {
+            x_min: self.x_min,
+            x_max: self.x_max,
+            y_min: self.y_min,
+            y_max: self.y_max,
+        };
+        // Derived traces have no samples of their own to save
+        let traces = GraphElement::iter(&self.elements)
+            .filter(|elem| elem.borrow().derivation.is_none())
+            .map(|elem| elem.borrow().to_session())
+            .collect();
+        (view, traces)
+    }
+
+    fn trace_reference_name(&self) -> String {
+        match self.trace_reference.and_then(|id| self.index_of(id)) {
+            Some(index) => format!("B: {}", self.elements.get(index).unwrap().borrow().name),
+            None => "No reference".to_string(),
+        }
+    }
+
+    /// Accumulate the sweeps of the trace being measured afresh, with the current settings.
+    fn restart_accumulation(&mut self) {
+        let Some(element) = self.active.and_then(|active| self.elements.get(active)) else {
+            return;
+        };
+        element.borrow_mut().accumulator = Accumulator::new(self.accumulation, self.average_depth);
+    }
+
+    /// Add a trace holding the extremes of the trace being measured, if there is one.
+    fn add_hold(&mut self, hold: Accumulation, sender: &ComponentSender<Self>) {
+        let Some(active) = self.active.and_then(|active| self.elements.get(active)) else {
+            return;
+        };
+        let element = {
+            let active = active.borrow();
+            GraphElement {
+                device: active.device.clone(),
+                timestamp: active.timestamp,
+                params: active.params,
+                accumulator: Accumulator::new(hold, self.average_depth),
+                ..GraphElement::new(
+                    format!("{} {}", active.name, hold.to_string().to_lowercase()),
+                    active.x_min.get(),
+                    active.x_max.get(),
+                    sender.input_sender().clone(),
+                )
+            }
+        };
+        self.holds.push((hold, element.id));
+        self.append_element(element, sender);
+    }
+
+    fn append_element(&mut self, element: GraphElement, sender: &ComponentSender<Self>) {
+        let sender = sender.clone();
+        element.visible.connect_value_notify(move |_| sender.input(Input::Redraw));
+        self.elements.append(element);
+    }
+
+    /// Position of the trace with `id` in the unsorted store.
+    fn index_of(&self, id: usize) -> Option<u32> {
+        find(&self.elements, |elem| elem.id == id)
+    }
+
+    /// Indices of the selected elements, or the active element if nothing is selected.
+    fn selected(&self) -> Vec<u32> {
+        // Selection positions are in the sorted view, not the store
+        let selected: Vec<u32> = (0..self.elements.len())
+            .filter(|i| self.elements.selection_model.is_selected(*i))
+            .filter_map(|i| self.elements.get_visible(i))
+            .filter_map(|elem| self.index_of(elem.borrow().id))
+            .collect();
+        if selected.is_empty() {
+            self.active.into_iter().collect()
+        } else {
+            selected
+        }
+    }
+
+    /// Selected traces that hold measured reflections, leaving out derived ones.
+    fn exportable(&self) -> Vec<u32> {
+        self.selected().into_iter()
+            .filter(|&index| {
+                let elem = self.elements.get(index).unwrap();
+                let elem = elem.borrow();
+                if elem.derivation.is_some() {
+                    warn!("{} is computed from other traces and can't be exported", elem.name);
+                }
+                elem.derivation.is_none()
+            })
+            .collect()
+    }
+
+    /// Add a trace combining each selected trace with the reference, in the chosen operation.
+    fn derive(&mut self, sender: &ComponentSender<Self>) {
+        let Some(reference) = self.trace_reference.and_then(|id| self.index_of(id)) else {
+            error!("pick a reference trace first");
+            return;
+        };
+        if self.elements.get(reference).unwrap().borrow().derivation.is_some() {
+            error!("a trace computed from others can't be the reference");
+            return;
+        }
+        let derived: Vec<_> = self.selected().into_iter()
+            .filter(|&index| index != reference)
+            .filter_map(|index| {
+                let elem = self.elements.get(index).unwrap();
+                let reference = self.elements.get(reference).unwrap();
+                let (elem, reference) = (elem.borrow(), reference.borrow());
+                if elem.derivation.is_some() {
+                    error!("only measured traces can be combined, not {}", elem.name);
+                    return None;
+                }
+                let derivation = Derivation::new(self.operation, &elem.samples, &reference.samples);
+                let Some((x_min, x_max)) = derivation.span() else {
+                    error!("{} and {} have no frequencies in common", elem.name, reference.name);
+                    return None;
+                };
+                Some(GraphElement {
+                    derivation: Some(derivation),
+                    ..GraphElement::new(self.operation.trace_name(&elem.name, &reference.name),
+                                        x_min,
+                                        x_max,
+                                        sender.input_sender().clone())
+                })
+            })
+            .collect();
+        if derived.is_empty() {
+            warn!("select the traces to combine with the reference");
+        }
+        for element in derived {
+            self.append_element(element, sender);
+        }
+    }
+
+    /// Export the selected traces, numbering the files if there is more than one.
+    fn export_touchstone(&self, path: &Path) {
+        let selected = self.exportable();
+        if selected.is_empty() {
+            error!("no trace selected for export");
+            return;
+        }
+        for (n, &index) in selected.iter().enumerate() {
+            let path = if selected.len() == 1 {
+                path.to_path_buf()
+            } else {
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                path.with_file_name(format!("{}_{}.s1p", stem, n + 1))
+            };
+            let elem = self.elements.get(index).unwrap();
+            let elem = elem.borrow();
+            let result = File::create(&path)
+                .and_then(|f| touchstone::write(BufWriter::new(f), &elem.name, &elem.samples));
+            match result {
+                Ok(()) => info!("exported {} to {}", elem.name, path.display()),
+                Err(e) => error!("exporting {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    /// Draw on the next frame, however many changes arrive before it.
+    fn queue_frame(&mut self, sender: &ComponentSender<Self>) {
+        if self.frame_pending {
+            return;
+        }
+        self.frame_pending = true;
+        let sender = sender.input_sender().clone();
+        self.draw_handler.drawing_area().add_tick_callback(move |_, _| {
+            sender.emit(Input::Frame);
+            gtk::glib::ControlFlow::Break
+        });
+    }
+
     fn draw(&mut self) {
+        match self.view {
+            View::Cartesian => self.draw_cartesian(),
+            View::Polar => self.draw_polar(),
+        }
+    }
+
+    fn draw_cartesian(&mut self) {
         let size = self.draw_handler.drawing_area().allocation();
         let w = size.width();
         let h = size.height();
@@ -234,13 +1080,21 @@ impl Graph {
             .margin(20)
             .x_label_area_size(40)
             .y_label_area_size(60)
-            .build_cartesian_2d(self.x_min..self.x_max, self.y_min..self.y_max).unwrap();
+            .build_cartesian_2d(self.x_min..self.x_max, self.axis(self.y_min)..self.axis(self.y_max)).unwrap();
 
-        chart.configure_mesh()
-            .x_desc("Frequency [MHz]")
+        let log_label = |y: &f32| format!("{:.2}", 10f32.powf(*y));
+        let mut mesh = chart.configure_mesh();
+        mesh.x_desc("Frequency [MHz]")
             .x_label_formatter(&|x| format!("{:.2}", x / 1000000.0))
-            .y_desc("Voltage [dB]")
-            .draw().unwrap();
+            .y_desc(self.mode.axis_label());
+        if self.log_axis() {
+            mesh.y_label_formatter(&log_label);
+        }
+        mesh.draw().unwrap();
+
+        if self.show_bands {
+            self.draw_bands(&mut chart);
+        }
 
         for elem in GraphElement::iter(&self.elements) {
             let elem = elem.borrow();
@@ -251,7 +1105,7 @@ impl Graph {
 
             chart
                 .draw_series(LineSeries::new(
-                    elem.samples.iter().copied(),
+                    elem.points(self.mode).map(|(x, y)| (x, self.axis(y))),
                     RGBColor(
                         (color.red() * 255.0) as u8,
                         (color.green() * 255.0) as u8,
@@ -262,33 +1116,234 @@ impl Graph {
                 .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], RED));
         }
 
-        if let Some(point) = self.pointer
+        self.draw_dips(&mut chart);
+        self.draw_markers(&mut chart);
+
+        if let Some((id, point)) = self.pointer
             .and_then(|(x, y)| chart.as_coord_spec().reverse_translate((x as i32, y as i32)))
             .and_then(|p| self.get_closest(p)) {
-            chart.plotting_area().draw(&Cross::new(point, 10, BLACK)).unwrap();
+            chart.plotting_area().draw(&Cross::new((point.0, self.axis(point.1)), 10, BLACK)).unwrap();
             root.draw_text(
-                &format!("({:.3} MHz, {:.3} dBV)", point.0 / 1000000.0, point.1),
+                &format!("({:.3} MHz, {})", point.0 / 1000000.0, self.format_value(id, point.1)),
                 &("sans-serif", 10, &BLACK).into_text_style(chart.plotting_area()),
                 (0, h - 10),
             )
                 .unwrap();
         };
 
+        if let Some((start, stop)) = self.selection {
+            chart.plotting_area().draw(&Rectangle::new(
+                [(start, self.axis(self.y_min)), (stop, self.axis(self.y_max))],
+                GREEN.mix(0.15).filled(),
+            )).unwrap();
+        }
+
+        if let Some(Drag { mode: DragMode::Zoom, start: (x, y), offset: (dx, dy), .. }) = &self.drag {
+            let corners = [(*x as i32, *y as i32), ((x + dx) as i32, (y + dy) as i32)];
+            root.draw(&Rectangle::new(corners, BLUE.mix(0.2).filled())).unwrap();
+            root.draw(&Rectangle::new(corners, BLUE)).unwrap();
+        }
+
         root.present().unwrap();
+        self.coords = Some(chart.as_coord_spec().clone());
+    }
+
+    fn extents(&self) -> Extents {
+        Extents {
+            x_min: self.x_min,
+            x_max: self.x_max,
+            y_min: self.y_min,
+            y_max: self.y_max,
+        }
+    }
+
+    /// Select the frequencies between the start of the drag and the pointer.
+    fn select(&mut self, drag: &Drag) {
+        let (x1, _) = to_data(&drag.coords, drag.start);
+        let (x2, _) = to_data(&drag.coords, (drag.start.0 + drag.offset.0, drag.start.1));
+        self.selection = (x1 != x2).then_some((x1.min(x2).max(0.0), x1.max(x2).max(0.0)));
+    }
+
+    /// Move the view the drag started with along with the pointer.
+    fn pan(&mut self, drag: &Drag) {
+        let (x1, y1) = to_data(&drag.coords, drag.start);
+        let (x2, y2) = to_data(&drag.coords, (drag.start.0 + drag.offset.0, drag.start.1 + drag.offset.1));
+        let Extents { x_min, x_max, y_min, y_max } = drag.view;
+        (self.x_min, self.x_max) = (x_min - (x2 - x1), x_max - (x2 - x1));
+        (self.y_min, self.y_max) = (self.value(self.axis(y_min) - (y2 - y1)), self.value(self.axis(y_max) - (y2 - y1)));
+    }
+
+    /// Value of a marker in the current mode, at the point of its trace nearest to its frequency.
+    fn marker_reading(&self, marker: &Marker) -> Option<(f32, f32)> {
+        let elem = self.elements.get(self.index_of(marker.element)?)?;
+        let reading = elem.borrow().value_at(marker.freq, self.mode);
+        reading
+    }
+
+    /// Readout of `value` on the trace with `id`, as derived traces don't plot the quantity itself.
+    fn format_value(&self, id: usize, value: f32) -> String {
+        match self.index_of(id).and_then(|index| self.elements.get(index)) {
+            Some(elem) => elem.borrow().format_value(self.mode, value),
+            None => self.mode.format_value(value),
+        }
+    }
+
+    fn format_delta(&self, id: usize, delta: f32) -> String {
+        match self.index_of(id).and_then(|index| self.elements.get(index)) {
+            Some(elem) => elem.borrow().format_delta(self.mode, delta),
+            None => self.mode.format_delta(delta),
+        }
+    }
+
+    /// Refresh the readouts in the marker table.
+    fn update_markers(&self) {
+        let reference = self.reference
+            .and_then(|number| find(&self.markers, |m| m.number == number))
+            .and_then(|index| self.marker_reading(&self.markers.get(index)?.borrow()));
+        for marker in Marker::iter(&self.markers) {
+            let marker = marker.borrow();
+            let reading = self.marker_reading(&marker);
+            let (value, delta_freq, delta_value) = match (reading, reference) {
+                (None, _) => ("–".to_string(), String::new(), String::new()),
+                (Some((_, value)), _) if self.reference == Some(marker.number) => {
+                    (self.format_value(marker.element, value), "reference".to_string(), String::new())
+                }
+                (Some((_, value)), None) => (self.format_value(marker.element, value), String::new(), String::new()),
+                (Some((freq, value)), Some((ref_freq, ref_value))) => (
+                    self.format_value(marker.element, value),
+                    format!("{:+.1} kHz", (freq - ref_freq) / 1000.0),
+                    self.format_delta(marker.element, value - ref_value),
+                ),
+            };
+            marker.value.set(value);
+            marker.delta_freq.set(delta_freq);
+            marker.delta_value.set(delta_value);
+        }
+    }
+
+    fn remove_markers(&mut self, element: usize) {
+        while let Some(index) = find(&self.markers, |m| m.element == element) {
+            let number = self.markers.get(index).unwrap().borrow().number;
+            if self.reference == Some(number) {
+                self.reference = None;
+            }
+            self.markers.remove(index);
+        }
+    }
+
+    /// Shade the bands overlapping the view, with their names along the top.
+    fn draw_bands<DB: DrawingBackend>(&self, chart: &mut ChartContext<DB, Cartesian2d<RangedCoordf32, RangedCoordf32>>) {
+        let (y_bottom, y_top) = (self.axis(self.y_min), self.axis(self.y_max));
+        let font = ("sans-serif", 10).into_font().color(&BLACK.mix(0.6));
+
+        for band in &self.bands {
+            let (start, stop) = (band.start_freq as f32, band.stop_freq as f32);
+            if stop < self.x_min || start > self.x_max {
+                continue;
+            }
+            let (start, stop) = (start.max(self.x_min), stop.min(self.x_max));
+            let plot = chart.plotting_area();
+            plot.draw(&Rectangle::new([(start, y_bottom), (stop, y_top)], RGBColor(255, 165, 0).mix(0.12).filled())).unwrap();
+            plot.draw(&(EmptyElement::at((start, y_top)) + Text::new(band.name.clone(), (3, 3), font.clone()))).unwrap();
+        }
+    }
+
+    /// Draw the user placed markers on the visible traces.
+    fn draw_markers<DB: DrawingBackend>(&self, chart: &mut ChartContext<DB, Cartesian2d<RangedCoordf32, RangedCoordf32>>) {
+        let (y_low, y_high) = (self.y_min.min(self.y_max), self.y_min.max(self.y_max));
+        let font = ("sans-serif", 12).into_font();
+
+        let markers: Vec<((f32, f32), String)> = Marker::iter(&self.markers).filter_map(|marker| {
+            let marker = marker.borrow();
+            let elem = self.elements.get(self.index_of(marker.element)?)?;
+            if !elem.borrow().visible.get() {
+                return None;
+            }
+            let point = self.marker_reading(&marker)?;
+            let label = match self.reference == Some(marker.number) {
+                true => format!("{} (ref)", marker.label()),
+                false => marker.label(),
+            };
+            Some((point, label))
+        }).filter(|&((x, y), _)| (self.x_min..=self.x_max).contains(&x) && (y_low..=y_high).contains(&y))
+            .collect();
+
+        chart.draw_series(markers.into_iter().map(|((x, y), label)| {
+            EmptyElement::at((x, self.axis(y)))
+                + TriangleMarker::new((0, -6), 6, BLACK.filled())
+                + Text::new(label, (-8, -26), font.clone().color(&BLACK))
+        })).unwrap();
     }
 
-    fn get_closest(&self, (x, y): (f32, f32)) -> Option<(f32, f32)> {
+    /// Mark the bottom and the bandwidth edges of every dip in the visible traces.
+    fn draw_dips<DB: DrawingBackend>(&self, chart: &mut ChartContext<DB, Cartesian2d<RangedCoordf32, RangedCoordf32>>) {
+        let (y_low, y_high) = (self.y_min.min(self.y_max), self.y_min.max(self.y_max));
+        let in_view = |&(x, y): &(f32, f32)| {
+            (self.x_min..=self.x_max).contains(&x) && (y_low..=y_high).contains(&y)
+        };
+        let font = ("sans-serif", 10).into_font();
+
+        for elem in GraphElement::iter(&self.elements) {
+            let elem = elem.borrow();
+
+            if !elem.visible.get() { continue; }
+
+            let color = elem.color.get();
+            let color = RGBColor(
+                (color.red() * 255.0) as u8,
+                (color.green() * 255.0) as u8,
+                (color.blue() * 255.0) as u8,
+            );
+
+            let mut markers = vec![];
+            for dip in &elem.dips {
+                let (_, reflection) = dip.minimum;
+                let value = self.mode.value(reflection);
+                markers.push((
+                    (dip.resonance, value),
+                    format!("{:.3} MHz, {}", dip.resonance / 1000000.0, self.mode.format_value(value)),
+                ));
+                for (edges, swr) in [(dip.edges_narrow, NARROW_SWR), (dip.edges_wide, WIDE_SWR)] {
+                    let value = self.mode.value(Reflection::from_swr(swr));
+                    for freq in edges.into_iter().flat_map(|(lower, upper)| [lower, upper]) {
+                        markers.push(((freq, value), format!("{}:1", swr)));
+                    }
+                }
+            }
+
+            chart.draw_series(markers.into_iter()
+                .filter(|(point, _)| in_view(point))
+                .map(|((x, y), label)| {
+                    EmptyElement::at((x, self.axis(y)))
+                        + Circle::new((0, 0), 3, color.filled())
+                        + Text::new(label, (5, -12), font.clone().color(&BLACK))
+                })).unwrap();
+        }
+    }
+
+    /// Point of the visible trace closest to the chart position `(x, y)`, with the id of its trace.
+    fn get_closest(&self, (x, y): (f32, f32)) -> Option<(usize, (f32, f32))> {
+        let y = self.value(y);
         let points: Vec<_> = GraphElement::iter(&self.elements).filter_map(|elem| {
             let elem = elem.borrow();
 
             if !elem.visible.get() { return None; }
 
-            let i = elem.samples.binary_search_by(|this| this.0.total_cmp(&x))
-                .unwrap_or_else(|i| i.saturating_sub(1));
-            elem.samples.get(i).cloned()
+            elem.value_at(x, self.mode).map(|point| (elem.id, point))
         }).collect();
         points.iter()
-            .min_by(|(_, y1), (_, y2)| (y - y1).abs().total_cmp(&(y - y2).abs()))
+            .min_by(|(_, (_, y1)), (_, (_, y2))| (y - y1).abs().total_cmp(&(y - y2).abs()))
             .cloned()
     }
-}
\ No newline at end of file
+}
+
+/// Axis values at a pixel, extrapolating beyond the plotting area.
+fn to_data(coords: &Cartesian2d<RangedCoordf32, RangedCoordf32>, (x, y): (f64, f64)) -> (f32, f32) {
+    let (x_range, y_range) = (coords.get_x_range(), coords.get_y_range());
+    let (x_pixels, y_pixels) = (coords.get_x_axis_pixel_range(), coords.get_y_axis_pixel_range());
+    let scale = |value: f64, pixels: &std::ops::Range<i32>, range: &std::ops::Range<f32>| {
+        let fraction = (value as f32 - pixels.start as f32) / (pixels.end - pixels.start) as f32;
+        range.start + fraction * (range.end - range.start)
+    };
+    (scale(x, &x_pixels, &x_range), scale(y, &y_pixels, &y_range))
+}
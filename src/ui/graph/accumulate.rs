@@ -0,0 +1,134 @@
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+
+/// Sweeps averaged over unless the user picks another number
+pub const DEFAULT_DEPTH: usize = 8;
+
+/// How repeated sweeps of the same range are combined per point.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum Accumulation {
+    /// Every sweep replaces the last
+    #[default]
+    Off,
+    /// Mean of the last sweeps
+    Mean,
+    /// Exponential average, weighting each new sweep by one over the depth
+    Exponential,
+    /// Worst match seen, the highest reflection, kept in a trace of its own
+    MaxHold,
+    /// Best match seen, the lowest reflection, kept in a trace of its own
+    MinHold,
+}
+
+impl Accumulation {
+    /// Ways of averaging the trace being measured.
+    pub const AVERAGES: [Accumulation; 3] = [Accumulation::Off, Accumulation::Mean, Accumulation::Exponential];
+
+    /// Whether the number of sweeps to average over matters.
+    pub fn averages(self) -> bool {
+        matches!(self, Accumulation::Mean | Accumulation::Exponential)
+    }
+}
+
+impl Display for Accumulation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Accumulation::Off => write!(f, "Off"),
+            Accumulation::Mean => write!(f, "Average"),
+            Accumulation::Exponential => write!(f, "Exponential average"),
+            Accumulation::MaxHold => write!(f, "Max hold"),
+            Accumulation::MinHold => write!(f, "Min hold"),
+        }
+    }
+}
+
+/// Combines the reflection measured at each point over the sweeps of a trace.
+pub(super) struct Accumulator {
+    accumulation: Accumulation,
+    depth: usize,
+    /// Accumulated reflection of each point and the number of sweeps that measured it
+    points: Vec<(f32, usize)>,
+    /// Last `depth` reflections of each point, for the mean
+    history: Vec<VecDeque<f32>>,
+}
+
+impl Accumulator {
+    pub(super) fn new(accumulation: Accumulation, depth: usize) -> Self {
+        Self {
+            accumulation,
+            depth: depth.max(1),
+            points: vec![],
+            history: vec![],
+        }
+    }
+
+    /// Forget the sweeps so far, the next one starts over.
+    pub(super) fn reset(&mut self) {
+        self.points.clear();
+        self.history.clear();
+    }
+
+    /// Take the reflection measured at point `index` and return what to plot there.
+    pub(super) fn add(&mut self, index: usize, value: f32) -> f32 {
+        if self.points.len() <= index {
+            self.points.resize(index + 1, (0.0, 0));
+        }
+        let (accumulated, count) = &mut self.points[index];
+        *count += 1;
+        *accumulated = match self.accumulation {
+            Accumulation::Mean => {
+                if self.history.len() <= index {
+                    self.history.resize(index + 1, VecDeque::new());
+                }
+                let history = &mut self.history[index];
+                history.push_back(value);
+                if history.len() > self.depth {
+                    history.pop_front();
+                }
+                history.iter().sum::<f32>() / history.len() as f32
+            }
+            _ if *count == 1 => value,
+            Accumulation::Off => value,
+            // Until there are enough sweeps this is the plain mean, so the start isn't biased
+            Accumulation::Exponential => *accumulated + (value - *accumulated) / (*count).min(self.depth) as f32,
+            Accumulation::MaxHold => accumulated.max(value),
+            Accumulation::MinHold => accumulated.min(value),
+        };
+        *accumulated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed `values` to point 0 in turn, returning what is plotted after each.
+    fn run(accumulation: Accumulation, depth: usize, values: &[f32]) -> Vec<f32> {
+        let mut accumulator = Accumulator::new(accumulation, depth);
+        values.iter().map(|&value| accumulator.add(0, value)).collect()
+    }
+
+    #[test]
+    fn mean_of_last_sweeps() {
+        assert_eq!(run(Accumulation::Mean, 2, &[0.2, 0.4, 0.6]), [0.2, 0.3, 0.5]);
+    }
+
+    #[test]
+    fn exponential_starts_as_mean() {
+        assert_eq!(run(Accumulation::Exponential, 2, &[0.2, 0.4, 0.8]), [0.2, 0.3, 0.55]);
+    }
+
+    #[test]
+    fn holds() {
+        assert_eq!(run(Accumulation::MaxHold, 8, &[0.2, 0.5, 0.3]), [0.2, 0.5, 0.5]);
+        assert_eq!(run(Accumulation::MinHold, 8, &[0.2, 0.1, 0.3]), [0.2, 0.1, 0.1]);
+    }
+
+    #[test]
+    fn reset_starts_over() {
+        let mut accumulator = Accumulator::new(Accumulation::MaxHold, 8);
+        accumulator.add(3, 0.9);
+        accumulator.reset();
+        assert_eq!(accumulator.add(3, 0.1), 0.1);
+    }
+}
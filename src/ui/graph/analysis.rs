@@ -0,0 +1,90 @@
+use crate::protocol::measurement::Reflection;
+
+/// SWR of the inner bandwidth edges
+pub(super) const NARROW_SWR: f32 = 1.5;
+/// SWR of the outer bandwidth edges, below which a stretch of the trace counts as a dip
+pub(super) const WIDE_SWR: f32 = 2.0;
+
+/// A dip in a trace around one of its SWR minima.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(super) struct Dip {
+    /// Frequency and reflection of the lowest sample
+    pub(super) minimum: (f32, Reflection),
+    /// Frequency of the bottom of the dip, interpolated between the samples around the minimum
+    pub(super) resonance: f32,
+    /// Frequencies where the SWR rises through 1.5:1, if both are inside the trace
+    pub(super) edges_narrow: Option<(f32, f32)>,
+    /// Frequencies where the SWR rises through 2:1, if both are inside the trace
+    pub(super) edges_wide: Option<(f32, f32)>,
+}
+
+/// Find the dips in samples of (frequency, |Γ|).
+///
+/// Every stretch below 2:1 is a dip, so noise on the bottom of a dip doesn't split it. A trace
+/// that never gets below 2:1 has a single dip at its lowest sample.
+pub(super) fn find_dips(samples: &[(f32, f32)]) -> Vec<Dip> {
+    let mut points: Vec<(f32, f32)> = samples.iter()
+        .copied()
+        // Steps that haven't been measured yet are left at zero frequency
+        .filter(|&(freq, reflection)| freq > 0.0 && reflection.is_finite())
+        .collect();
+    points.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let wide = Reflection::from_swr(WIDE_SWR).magnitude();
+    let mut minima = vec![];
+    let mut run: Option<usize> = None;
+    for (i, &(_, reflection)) in points.iter().enumerate() {
+        if reflection < wide {
+            run = match run {
+                Some(m) if points[m].1 <= reflection => Some(m),
+                _ => Some(i),
+            };
+        } else if let Some(m) = run.take() {
+            minima.push(m);
+        }
+    }
+    minima.extend(run);
+    if minima.is_empty() {
+        minima.extend((0..points.len()).min_by(|&a, &b| points[a].1.total_cmp(&points[b].1)));
+    }
+
+    minima.into_iter().map(|m| Dip {
+        minimum: (points[m].0, Reflection::new(points[m].1)),
+        resonance: vertex(&points, m),
+        edges_narrow: edges(&points, m, NARROW_SWR),
+        edges_wide: edges(&points, m, WIDE_SWR),
+    }).collect()
+}
+
+/// Frequency of the bottom of a parabola through the minimum and its neighbours.
+fn vertex(points: &[(f32, f32)], m: usize) -> f32 {
+    let (x2, y2) = points[m];
+    let (Some(&(x1, y1)), Some(&(x3, y3))) = (m.checked_sub(1).and_then(|i| points.get(i)), points.get(m + 1)) else {
+        return x2;
+    };
+    let numerator = (x2 - x1).powi(2) * (y2 - y3) - (x2 - x3).powi(2) * (y2 - y1);
+    let denominator = (x2 - x1) * (y2 - y3) - (x2 - x3) * (y2 - y1);
+    if denominator == 0.0 {
+        return x2;
+    }
+    (x2 - 0.5 * numerator / denominator).clamp(x1, x3)
+}
+
+/// Frequencies either side of the minimum where the SWR rises through `swr`.
+fn edges(points: &[(f32, f32)], m: usize, swr: f32) -> Option<(f32, f32)> {
+    let threshold = Reflection::from_swr(swr).magnitude();
+    if points[m].1 >= threshold {
+        return None;
+    }
+    let lower = (0..m).rev().find(|&i| points[i].1 >= threshold)?;
+    let upper = (m + 1..points.len()).find(|&i| points[i].1 >= threshold)?;
+    Some((
+        crossing(points[lower + 1], points[lower], threshold),
+        crossing(points[upper - 1], points[upper], threshold),
+    ))
+}
+
+/// Linearly interpolated frequency between a point below and a point above `threshold`.
+fn crossing((x1, y1): (f32, f32), (x2, y2): (f32, f32), threshold: f32) -> f32 {
+    x1 + (x2 - x1) * (threshold - y1) / (y2 - y1)
+}
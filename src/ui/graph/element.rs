@@ -1,43 +1,228 @@
-use gtk4::{GestureClick, ListItem, MultiSelection};
-use gtk4::glib::{SignalHandlerId, WeakRef};
-use gtk4::glib::clone::Downgrade;
-use gtk4::prelude::{ButtonExt, DrawingAreaExtManual, GdkCairoContextExt, ListItemExt, ObjectExt, WidgetExt};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use chrono::{DateTime, Local};
+use gtk4::{GestureClick, hsv_to_rgb, ListItem, MultiSelection};
+use gtk4::gdk::RGBA;
+use gtk4::glib::SignalHandlerId;
+use gtk4::prelude::{ButtonExt, DrawingAreaExtManual, GdkCairoContextExt, ObjectExt, WidgetExt};
 use relm4::{gtk, RelmObjectExt, Sender};
 use relm4::binding::{Binding, BoolBinding, F32Binding};
-use relm4::typed_view::column::{RelmColumn, TypedColumnView};
+use rand::{Rng, thread_rng};
+use relm4::typed_view::column::{LabelColumn, RelmColumn, TypedColumnView};
 use relm4::typed_view::TypedListItem;
 
 use crate::ui::graph;
+use crate::format::session::{Extents, SessionTrace};
+use crate::format::Trace;
+use crate::protocol::measurement::Reflection;
+use crate::protocol::SweepParams;
+use crate::ui::graph::accumulate::{Accumulation, Accumulator, DEFAULT_DEPTH};
+use crate::ui::graph::analysis::{Dip, find_dips};
+use crate::ui::graph::math::Derivation;
 use crate::ui::graph::color_binding::RGBABinding;
+use crate::ui::graph::mode::GraphMode;
 use crate::ui::swr_worker::Sample;
 use crate::ui::util::{BindingLabelColumn, BindingLabelColumnWrapper};
 
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
 pub(super) struct GraphElement {
+    /// Identifies the trace independent of its position in the sorted view
+    pub(super) id: usize,
+    pub(super) name: String,
     pub(super) visible: BoolBinding,
     pub(super) x_min: F32Binding,
     pub(super) x_max: F32Binding,
     pub(super) y_min: F32Binding,
     pub(super) y_max: F32Binding,
     pub(super) samples: Vec<(f32, f32)>,
+    pub(super) dips: Vec<Dip>,
+    /// Figures of the deepest dip, NaN while there is none
+    pub(super) resonance: F32Binding,
+    pub(super) min_swr: F32Binding,
+    pub(super) bandwidth_narrow: F32Binding,
+    pub(super) bandwidth_wide: F32Binding,
     pub(super) color: RGBABinding,
+    pub(super) device: Option<String>,
+    pub(super) timestamp: Option<DateTime<Local>>,
+    pub(super) params: Option<SweepParams>,
+    /// Combines repeated sweeps into the plotted samples
+    pub(super) accumulator: Accumulator,
+    /// How the trace is computed from others, if it wasn't measured
+    pub(super) derivation: Option<Derivation>,
     pub(super) sender: Sender<graph::Input>,
 }
 
 impl GraphElement {
-    pub(super) fn push_sample(&mut self, sample: Sample) {
-        let Sample { index, freq, value } = sample;
-        if self.samples.len() <= index {
-            self.samples.resize(index + 1, (0.0, 0.0));
+    /// Empty trace with a random color, spanning `x_min` to `x_max`.
+    pub(super) fn new(name: String, x_min: f32, x_max: f32, sender: Sender<graph::Input>) -> Self {
+        let (r, g, b) = hsv_to_rgb(thread_rng().gen_range(0.0..1.0), 1.0, 1.0);
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            name,
+            visible: BoolBinding::new(true),
+            x_min: F32Binding::new(x_min),
+            x_max: F32Binding::new(x_max),
+            y_min: F32Binding::new(1.0),
+            y_max: F32Binding::new(0.0),
+            samples: vec![],
+            dips: vec![],
+            resonance: F32Binding::new(f32::NAN),
+            min_swr: F32Binding::new(f32::NAN),
+            bandwidth_narrow: F32Binding::new(f32::NAN),
+            bandwidth_wide: F32Binding::new(f32::NAN),
+            color: RGBABinding::new(RGBA::new(r, g, b, 1.0)),
+            device: None,
+            timestamp: None,
+            params: None,
+            accumulator: Accumulator::new(Accumulation::Off, DEFAULT_DEPTH),
+            derivation: None,
+            sender,
+        }
+    }
+
+    /// Trace of previously measured or imported samples.
+    pub(super) fn from_samples(name: String, samples: Vec<(f32, f32)>, sender: Sender<graph::Input>) -> Self {
+        let x_min = samples.first().map_or(0.0, |(freq, _)| *freq);
+        let x_max = samples.last().map_or(0.0, |(freq, _)| *freq);
+        let this = Self::new(name, x_min, x_max, sender);
+        for &(_, value) in &samples {
+            this.y_min.set(this.y_min.get().min(value));
+            this.y_max.set(this.y_max.get().max(value));
+        }
+        let mut this = Self { samples, ..this };
+        this.analyse();
+        this
+    }
+
+    /// Trace restored from a file, keeping its color if it has one.
+    pub(super) fn from_trace(trace: Trace, sender: Sender<graph::Input>) -> Self {
+        let Trace { name, color, device, timestamp, params, samples } = trace;
+        let this = Self::from_samples(name, samples, sender);
+        if let Some(color) = color.and_then(|c| RGBA::parse(c).ok()) {
+            this.color.set(color);
         }
-        self.samples[sample.index] = (freq, value);
-        let mut x_max = self.x_max.guard();
-        let mut x_min = self.x_min.guard();
-        let mut y_max = self.y_max.guard();
-        let mut y_min = self.y_min.guard();
-        *x_max = x_max.max(freq);
-        *x_min = x_min.min(freq);
-        *y_max = y_max.max(value);
-        *y_min = y_min.min(value);
+        Self { device, timestamp, params, ..this }
+    }
+
+    pub(super) fn from_session(session: SessionTrace, sender: Sender<graph::Input>) -> Self {
+        let SessionTrace { trace, visible, extents } = session;
+        let this = Self::from_trace(trace, sender);
+        this.visible.set(visible);
+        this.x_min.set(extents.x_min);
+        this.x_max.set(extents.x_max);
+        this.y_min.set(extents.y_min);
+        this.y_max.set(extents.y_max);
+        this
+    }
+
+    pub(super) fn to_session(&self) -> SessionTrace {
+        SessionTrace {
+            trace: self.to_trace(),
+            visible: self.visible.get(),
+            extents: Extents {
+                x_min: self.x_min.get(),
+                x_max: self.x_max.get(),
+                y_min: self.y_min.get(),
+                y_max: self.y_max.get(),
+            },
+        }
+    }
+
+    pub(super) fn to_trace(&self) -> Trace {
+        let color = self.color.get();
+        Trace {
+            name: self.name.clone(),
+            color: Some(format!("#{:02x}{:02x}{:02x}",
+                                (color.red() * 255.0) as u8,
+                                (color.green() * 255.0) as u8,
+                                (color.blue() * 255.0) as u8)),
+            device: self.device.clone(),
+            timestamp: self.timestamp,
+            params: self.params,
+            samples: self.samples.clone(),
+        }
+    }
+
+    /// Store a batch of measured samples, analysing the trace once for all of them.
+    pub(super) fn push_samples(&mut self, samples: Vec<Sample>) {
+        {
+            let mut x_max = self.x_max.guard();
+            let mut x_min = self.x_min.guard();
+            let mut y_max = self.y_max.guard();
+            let mut y_min = self.y_min.guard();
+            for Sample { index, freq, reflection, .. } in samples {
+                let value = self.accumulator.add(index, reflection.magnitude());
+                if self.samples.len() <= index {
+                    self.samples.resize(index + 1, (0.0, 0.0));
+                }
+                self.samples[index] = (freq, value);
+                *x_max = x_max.max(freq);
+                *x_min = x_min.min(freq);
+                *y_max = y_max.max(value);
+                *y_min = y_min.min(value);
+            }
+        }
+        self.analyse();
+    }
+
+    /// Start accumulating sweeps over, forgetting the extents the earlier ones reached.
+    pub(super) fn restart_accumulation(&mut self) {
+        self.accumulator.reset();
+        self.y_min.set(1.0);
+        self.y_max.set(0.0);
+    }
+
+    /// Drop all samples, for a hold trace starting over.
+    pub(super) fn clear_samples(&mut self) {
+        self.samples.clear();
+        self.restart_accumulation();
+        self.analyse();
+    }
+
+    /// Find the dips again and update the figures shown for the deepest one.
+    fn analyse(&mut self) {
+        self.dips = find_dips(&self.samples);
+        let deepest = self.dips.iter()
+            .min_by(|a, b| a.minimum.1.magnitude().total_cmp(&b.minimum.1.magnitude()));
+        let bandwidth = |edges: Option<(f32, f32)>| edges.map_or(f32::NAN, |(lower, upper)| upper - lower);
+        self.resonance.set(deepest.map_or(f32::NAN, |dip| dip.resonance));
+        self.min_swr.set(deepest.map_or(f32::NAN, |dip| dip.minimum.1.swr()));
+        self.bandwidth_narrow.set(deepest.map_or(f32::NAN, |dip| bandwidth(dip.edges_narrow)));
+        self.bandwidth_wide.set(deepest.map_or(f32::NAN, |dip| bandwidth(dip.edges_wide)));
+    }
+
+    /// Point plotted in `mode` nearest to `freq`.
+    pub(super) fn value_at(&self, freq: f32, mode: GraphMode) -> Option<(f32, f32)> {
+        self.points(mode)
+            .filter(|(f, _)| *f > 0.0)
+            .min_by(|(a, _), (b, _)| (a - freq).abs().total_cmp(&(b - freq).abs()))
+    }
+
+    /// Readout of a value of this trace plotted in `mode`.
+    pub(super) fn format_value(&self, mode: GraphMode, value: f32) -> String {
+        match &self.derivation {
+            Some(derivation) => derivation.format_value(mode, value),
+            None => mode.format_value(value),
+        }
+    }
+
+    /// Readout of the difference between two values of this trace plotted in `mode`.
+    pub(super) fn format_delta(&self, mode: GraphMode, delta: f32) -> String {
+        match &self.derivation {
+            Some(derivation) => derivation.format_delta(mode, delta),
+            None => mode.format_delta(delta),
+        }
+    }
+
+    /// Samples converted to the quantity plotted in `mode`, skipping values that can't be drawn.
+    pub(super) fn points(&self, mode: GraphMode) -> Box<dyn Iterator<Item=(f32, f32)> + '_> {
+        if let Some(derivation) = &self.derivation {
+            return Box::new(derivation.points(mode));
+        }
+        Box::new(self.samples.iter()
+            .map(move |&(freq, reflection)| (freq, mode.value(Reflection::new(reflection))))
+            .filter(|(_, value)| value.is_finite()))
     }
 }
 
@@ -47,18 +232,20 @@ impl GraphElement {
 
         view.append_column::<VisibleColumn>();
         view.append_column::<ColorColumn>();
+        view.append_column::<NameColumn>();
         view.append_column::<BindingLabelColumnWrapper<MinFreqColumn>>();
         view.append_column::<BindingLabelColumnWrapper<MaxFreqColumn>>();
+        view.append_column::<BindingLabelColumnWrapper<ResonanceColumn>>();
+        view.append_column::<BindingLabelColumnWrapper<MinSwrColumn>>();
+        view.append_column::<BindingLabelColumnWrapper<NarrowBandwidthColumn>>();
+        view.append_column::<BindingLabelColumnWrapper<WideBandwidthColumn>>();
         view.append_column::<DeleteColumn>();
 
         view
     }
 
-    pub(super) fn iter(view: &TypedColumnView<GraphElement, MultiSelection>) -> ColumnViewIter<Self> {
-        ColumnViewIter {
-            view,
-            index: 0,
-        }
+    pub(super) fn iter(view: &TypedColumnView<GraphElement, MultiSelection>) -> ColumnViewIter<'_, Self> {
+        ColumnViewIter::new(view)
     }
 }
 
@@ -83,7 +270,6 @@ impl RelmColumn for VisibleColumn {
 struct ColorColumn {
     click_handler: GestureClick,
     handles: Option<[SignalHandlerId; 2]>,
-    list_item: WeakRef<ListItem>,
 }
 
 impl RelmColumn for ColorColumn {
@@ -92,14 +278,14 @@ impl RelmColumn for ColorColumn {
     type Item = GraphElement;
     const COLUMN_NAME: &'static str = "Color";
 
-    fn setup(list_item: &ListItem) -> (Self::Root, Self::Widgets) {
+    fn setup(_list_item: &ListItem) -> (Self::Root, Self::Widgets) {
         let widget = gtk::DrawingArea::new();
 
         let click_handler = GestureClick::new();
 
         widget.add_controller(click_handler.clone());
 
-        (widget, Self { click_handler, handles: None, list_item: Downgrade::downgrade(list_item) })
+        (widget, Self { click_handler, handles: None })
     }
 
     fn bind(item: &mut Self::Item, widgets: &mut Self::Widgets, root: &mut Self::Root) {
@@ -113,12 +299,10 @@ impl RelmColumn for ColorColumn {
         });
 
         let sender = item.sender.clone();
-        let list_item = widgets.list_item.clone();
+        let id = item.id;
 
         let click_handler = widgets.click_handler.connect_released(move |_, _, _, _| {
-            if let Some(item) = list_item.upgrade() {
-                sender.send(graph::Input::ColorPicker(item.position())).unwrap()
-            }
+            sender.send(graph::Input::ColorPicker(id)).unwrap()
         });
         
         let root = root.clone();
@@ -138,6 +322,19 @@ impl RelmColumn for ColorColumn {
     }
 }
 
+struct NameColumn;
+
+impl LabelColumn for NameColumn {
+    type Item = GraphElement;
+    type Value = String;
+    const COLUMN_NAME: &'static str = "Name";
+    const ENABLE_SORT: bool = false;
+
+    fn get_cell_value(item: &Self::Item) -> Self::Value {
+        item.name.clone()
+    }
+}
+
 struct MinFreqColumn;
 
 impl BindingLabelColumn for MinFreqColumn {
@@ -177,9 +374,93 @@ impl BindingLabelColumn for MaxFreqColumn {
     }
 }
 
+struct ResonanceColumn;
+
+impl BindingLabelColumn for ResonanceColumn {
+    type Item = GraphElement;
+    type Value = F32Binding;
+    type Target = f32;
+
+    const COLUMN_NAME: &'static str = "Resonance [MHz]";
+    const ENABLE_SORT: bool = true;
+
+    fn get_cell_value(item: &Self::Item) -> &Self::Value {
+        &item.resonance
+    }
+
+    fn format_cell_value(value: &f32) -> String {
+        format_figure(*value / 1000000.0, 3)
+    }
+}
+
+struct MinSwrColumn;
+
+impl BindingLabelColumn for MinSwrColumn {
+    type Item = GraphElement;
+    type Value = F32Binding;
+    type Target = f32;
+
+    const COLUMN_NAME: &'static str = "Min SWR";
+    const ENABLE_SORT: bool = true;
+
+    fn get_cell_value(item: &Self::Item) -> &Self::Value {
+        &item.min_swr
+    }
+
+    fn format_cell_value(value: &f32) -> String {
+        format_figure(*value, 2)
+    }
+}
+
+struct NarrowBandwidthColumn;
+
+impl BindingLabelColumn for NarrowBandwidthColumn {
+    type Item = GraphElement;
+    type Value = F32Binding;
+    type Target = f32;
+
+    const COLUMN_NAME: &'static str = "1.5:1 bandwidth [kHz]";
+    const ENABLE_SORT: bool = true;
+
+    fn get_cell_value(item: &Self::Item) -> &Self::Value {
+        &item.bandwidth_narrow
+    }
+
+    fn format_cell_value(value: &f32) -> String {
+        format_figure(*value / 1000.0, 1)
+    }
+}
+
+struct WideBandwidthColumn;
+
+impl BindingLabelColumn for WideBandwidthColumn {
+    type Item = GraphElement;
+    type Value = F32Binding;
+    type Target = f32;
+
+    const COLUMN_NAME: &'static str = "2:1 bandwidth [kHz]";
+    const ENABLE_SORT: bool = true;
+
+    fn get_cell_value(item: &Self::Item) -> &Self::Value {
+        &item.bandwidth_wide
+    }
+
+    fn format_cell_value(value: &f32) -> String {
+        format_figure(*value / 1000.0, 1)
+    }
+}
+
+/// Figure with `precision` decimals, or a dash when it couldn't be determined.
+fn format_figure(value: f32, precision: usize) -> String {
+    if value.is_finite() {
+        format!("{:.*}", precision, value)
+    } else {
+        "–".to_string()
+    }
+}
+
 struct DeleteColumn {
     signal_handle: Option<SignalHandlerId>,
-    list_item: WeakRef<ListItem>,
 }
 
 impl RelmColumn for DeleteColumn {
@@ -188,22 +469,20 @@ impl RelmColumn for DeleteColumn {
     type Item = GraphElement;
     const COLUMN_NAME: &'static str = "";
 
-    fn setup(list_item: &ListItem) -> (Self::Root, Self::Widgets) {
+    fn setup(_list_item: &ListItem) -> (Self::Root, Self::Widgets) {
         let widget = gtk::Button::builder()
             .label("Delete")
             .build();
 
-        (widget, Self { signal_handle: None, list_item: Downgrade::downgrade(list_item) })
+        (widget, Self { signal_handle: None })
     }
 
     fn bind(item: &mut Self::Item, widgets: &mut Self::Widgets, root: &mut Self::Root) {
         let sender = item.sender.clone();
-        let list_item = widgets.list_item.clone();
+        let id = item.id;
 
         widgets.signal_handle = Some(root.connect_clicked(move |_| {
-            if let Some(item) = list_item.upgrade() {
-                sender.emit(graph::Input::Delete(item.position()))
-            }
+            sender.emit(graph::Input::Delete(id))
         }));
     }
 
@@ -219,6 +498,22 @@ pub struct ColumnViewIter<'a, T: 'static> {
     index: u32,
 }
 
+impl<'a, T: 'static> ColumnViewIter<'a, T> {
+    pub(super) fn new(view: &'a TypedColumnView<T, MultiSelection>) -> Self {
+        Self {
+            view,
+            index: 0,
+        }
+    }
+}
+
+/// Store position of the first item matching `predicate`.
+pub(super) fn find<T: 'static>(view: &TypedColumnView<T, MultiSelection>, predicate: impl Fn(&T) -> bool) -> Option<u32> {
+    ColumnViewIter::new(view)
+        .position(|item| predicate(&item.borrow()))
+        .map(|i| i as u32)
+}
+
 impl<'a, T: 'static> Iterator for ColumnViewIter<'a, T> {
     type Item = TypedListItem<T>;
 
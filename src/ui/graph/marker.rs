@@ -0,0 +1,237 @@
+use gtk4::{ListItem, MultiSelection};
+use gtk4::glib::SignalHandlerId;
+use gtk4::prelude::{ButtonExt, ObjectExt};
+use relm4::{gtk, Sender};
+use relm4::binding::StringBinding;
+use relm4::typed_view::column::{LabelColumn, RelmColumn, TypedColumnView};
+
+use crate::ui::graph;
+use crate::ui::graph::element::ColumnViewIter;
+use crate::ui::util::{BindingLabelColumn, BindingLabelColumnWrapper};
+
+/// Marker pinned to a frequency on one trace, placed by clicking on the chart.
+pub(super) struct Marker {
+    /// Shown as M1, M2, ...; never reused while the graph is open
+    pub(super) number: usize,
+    /// Id of the trace the marker is pinned to
+    pub(super) element: usize,
+    pub(super) trace: String,
+    pub(super) freq: f32,
+    /// Readout in the current graph mode, updated as the trace changes
+    pub(super) value: StringBinding,
+    /// Difference to the reference marker
+    pub(super) delta_freq: StringBinding,
+    pub(super) delta_value: StringBinding,
+    pub(super) sender: Sender<graph::Input>,
+}
+
+impl Marker {
+    pub(super) fn new(number: usize, element: usize, trace: String, freq: f32, sender: Sender<graph::Input>) -> Self {
+        Self {
+            number,
+            element,
+            trace,
+            freq,
+            value: StringBinding::default(),
+            delta_freq: StringBinding::default(),
+            delta_value: StringBinding::default(),
+            sender,
+        }
+    }
+
+    pub(super) fn label(&self) -> String {
+        format!("M{}", self.number)
+    }
+
+    pub(super) fn column_view() -> TypedColumnView<Marker, MultiSelection> {
+        let mut view = TypedColumnView::new();
+
+        view.append_column::<NumberColumn>();
+        view.append_column::<TraceColumn>();
+        view.append_column::<FreqColumn>();
+        view.append_column::<BindingLabelColumnWrapper<ValueColumn>>();
+        view.append_column::<BindingLabelColumnWrapper<DeltaFreqColumn>>();
+        view.append_column::<BindingLabelColumnWrapper<DeltaValueColumn>>();
+        view.append_column::<ReferenceColumn>();
+        view.append_column::<DeleteColumn>();
+
+        view
+    }
+
+    pub(super) fn iter(view: &TypedColumnView<Marker, MultiSelection>) -> ColumnViewIter<'_, Self> {
+        ColumnViewIter::new(view)
+    }
+}
+
+struct NumberColumn;
+
+impl LabelColumn for NumberColumn {
+    type Item = Marker;
+    type Value = usize;
+    const COLUMN_NAME: &'static str = "Marker";
+    const ENABLE_SORT: bool = true;
+
+    fn get_cell_value(item: &Self::Item) -> Self::Value {
+        item.number
+    }
+
+    fn format_cell_value(value: &usize) -> String {
+        format!("M{}", value)
+    }
+}
+
+struct TraceColumn;
+
+impl LabelColumn for TraceColumn {
+    type Item = Marker;
+    type Value = String;
+    const COLUMN_NAME: &'static str = "Trace";
+    const ENABLE_SORT: bool = true;
+
+    fn get_cell_value(item: &Self::Item) -> Self::Value {
+        item.trace.clone()
+    }
+}
+
+struct FreqColumn;
+
+impl LabelColumn for FreqColumn {
+    type Item = Marker;
+    type Value = f32;
+    const COLUMN_NAME: &'static str = "Frequency [MHz]";
+    const ENABLE_SORT: bool = true;
+
+    fn get_cell_value(item: &Self::Item) -> Self::Value {
+        item.freq
+    }
+
+    fn format_cell_value(value: &f32) -> String {
+        format!("{:.4}", value / 1000000.0)
+    }
+}
+
+struct ValueColumn;
+
+impl BindingLabelColumn for ValueColumn {
+    type Item = Marker;
+    type Value = StringBinding;
+    type Target = String;
+    const COLUMN_NAME: &'static str = "Value";
+    const ENABLE_SORT: bool = false;
+    const ENABLE_EXPAND: bool = true;
+
+    fn get_cell_value(item: &Self::Item) -> &Self::Value {
+        &item.value
+    }
+
+    fn format_cell_value(value: &String) -> String {
+        value.clone()
+    }
+}
+
+struct DeltaFreqColumn;
+
+impl BindingLabelColumn for DeltaFreqColumn {
+    type Item = Marker;
+    type Value = StringBinding;
+    type Target = String;
+    const COLUMN_NAME: &'static str = "Δf";
+    const ENABLE_SORT: bool = false;
+    const ENABLE_EXPAND: bool = true;
+
+    fn get_cell_value(item: &Self::Item) -> &Self::Value {
+        &item.delta_freq
+    }
+
+    fn format_cell_value(value: &String) -> String {
+        value.clone()
+    }
+}
+
+struct DeltaValueColumn;
+
+impl BindingLabelColumn for DeltaValueColumn {
+    type Item = Marker;
+    type Value = StringBinding;
+    type Target = String;
+    const COLUMN_NAME: &'static str = "Δvalue";
+    const ENABLE_SORT: bool = false;
+    const ENABLE_EXPAND: bool = true;
+
+    fn get_cell_value(item: &Self::Item) -> &Self::Value {
+        &item.delta_value
+    }
+
+    fn format_cell_value(value: &String) -> String {
+        value.clone()
+    }
+}
+
+/// Button making the marker the reference of the delta readouts.
+struct ReferenceColumn {
+    signal_handle: Option<SignalHandlerId>,
+}
+
+impl RelmColumn for ReferenceColumn {
+    type Root = gtk::Button;
+    type Widgets = Self;
+    type Item = Marker;
+    const COLUMN_NAME: &'static str = "";
+
+    fn setup(_list_item: &ListItem) -> (Self::Root, Self::Widgets) {
+        let widget = gtk::Button::builder()
+            .label("Reference")
+            .build();
+
+        (widget, Self { signal_handle: None })
+    }
+
+    fn bind(item: &mut Self::Item, widgets: &mut Self::Widgets, root: &mut Self::Root) {
+        let sender = item.sender.clone();
+        let number = item.number;
+
+        widgets.signal_handle = Some(root.connect_clicked(move |_| {
+            sender.emit(graph::Input::SetReference(number))
+        }));
+    }
+
+    fn unbind(_item: &mut Self::Item, widgets: &mut Self::Widgets, root: &mut Self::Root) {
+        if let Some(x) = widgets.signal_handle.take() {
+            root.disconnect(x);
+        }
+    }
+}
+
+struct DeleteColumn {
+    signal_handle: Option<SignalHandlerId>,
+}
+
+impl RelmColumn for DeleteColumn {
+    type Root = gtk::Button;
+    type Widgets = Self;
+    type Item = Marker;
+    const COLUMN_NAME: &'static str = "";
+
+    fn setup(_list_item: &ListItem) -> (Self::Root, Self::Widgets) {
+        let widget = gtk::Button::builder()
+            .label("Delete")
+            .build();
+
+        (widget, Self { signal_handle: None })
+    }
+
+    fn bind(item: &mut Self::Item, widgets: &mut Self::Widgets, root: &mut Self::Root) {
+        let sender = item.sender.clone();
+        let number = item.number;
+
+        widgets.signal_handle = Some(root.connect_clicked(move |_| {
+            sender.emit(graph::Input::DeleteMarker(number))
+        }));
+    }
+
+    fn unbind(_item: &mut Self::Item, widgets: &mut Self::Widgets, root: &mut Self::Root) {
+        if let Some(x) = widgets.signal_handle.take() {
+            root.disconnect(x);
+        }
+    }
+}
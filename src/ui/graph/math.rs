@@ -0,0 +1,134 @@
+use std::fmt::{Display, Formatter};
+
+use crate::protocol::measurement::Reflection;
+use crate::ui::graph::mode::GraphMode;
+
+/// How a trace is combined with the reference trace.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum Operation {
+    #[default]
+    Difference,
+    Ratio,
+    /// Take the reference out, dividing linear quantities and subtracting decibels
+    Normalise,
+}
+
+impl Operation {
+    pub const ALL: [Operation; 3] = [Operation::Difference, Operation::Ratio, Operation::Normalise];
+
+    /// Combine `value` with `reference`, both in the quantity plotted in `mode`.
+    pub fn apply(self, mode: GraphMode, value: f32, reference: f32) -> f32 {
+        match self {
+            Operation::Difference => value - reference,
+            Operation::Ratio => value / reference,
+            Operation::Normalise => match mode {
+                GraphMode::ReturnLoss | GraphMode::MismatchLoss => value - reference,
+                GraphMode::Swr | GraphMode::Reflection => value / reference,
+            },
+        }
+    }
+
+    /// Whether the result is a ratio rather than a difference of the plotted quantity.
+    fn is_ratio(self, mode: GraphMode) -> bool {
+        match self {
+            Operation::Difference => false,
+            Operation::Ratio => true,
+            Operation::Normalise => matches!(mode, GraphMode::Swr | GraphMode::Reflection),
+        }
+    }
+
+    /// Name of the trace combining `trace` with `reference`.
+    pub fn trace_name(self, trace: &str, reference: &str) -> String {
+        match self {
+            Operation::Difference => format!("{} − {}", trace, reference),
+            Operation::Ratio => format!("{} / {}", trace, reference),
+            Operation::Normalise => format!("{} normalised to {}", trace, reference),
+        }
+    }
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operation::Difference => write!(f, "A − B"),
+            Operation::Ratio => write!(f, "A / B"),
+            Operation::Normalise => write!(f, "A normalised to B"),
+        }
+    }
+}
+
+/// Trace computed from two others as they were when it was created.
+///
+/// The result depends on the plotted quantity, so only the samples of both traces are kept and
+/// combined whenever the trace is drawn.
+pub(super) struct Derivation {
+    operation: Operation,
+    /// Measured samples, sorted by frequency
+    trace: Vec<(f32, f32)>,
+    reference: Vec<(f32, f32)>,
+}
+
+impl Derivation {
+    pub(super) fn new(operation: Operation, trace: &[(f32, f32)], reference: &[(f32, f32)]) -> Self {
+        Self {
+            operation,
+            trace: measured(trace),
+            reference: measured(reference),
+        }
+    }
+
+    /// Frequencies the result spans, those of the trace the reference covers.
+    pub(super) fn span(&self) -> Option<(f32, f32)> {
+        let mut freqs = self.trace.iter()
+            .map(|&(freq, _)| freq)
+            .filter(|&freq| interpolate(&self.reference, freq).is_some());
+        let first = freqs.next()?;
+        Some((first, freqs.next_back().unwrap_or(first)))
+    }
+
+    /// Readout of a result, which is a difference or ratio rather than the plotted quantity.
+    pub(super) fn format_value(&self, mode: GraphMode, value: f32) -> String {
+        match self.operation.is_ratio(mode) {
+            true => format!("ratio {:.3}", value),
+            false => mode.format_delta(value),
+        }
+    }
+
+    /// Readout of the difference between two results.
+    pub(super) fn format_delta(&self, mode: GraphMode, delta: f32) -> String {
+        match self.operation.is_ratio(mode) {
+            true => format!("Δratio {:+.3}", delta),
+            false => mode.format_delta(delta),
+        }
+    }
+
+    /// Result at the frequencies of the trace, with the reference interpolated between its samples.
+    pub(super) fn points(&self, mode: GraphMode) -> impl Iterator<Item=(f32, f32)> + '_ {
+        self.trace.iter()
+            .filter_map(move |&(freq, reflection)| {
+                let reference = interpolate(&self.reference, freq)?;
+                let value = self.operation.apply(mode,
+                                                 mode.value(Reflection::new(reflection)),
+                                                 mode.value(Reflection::new(reference)));
+                Some((freq, value))
+            })
+            .filter(|(_, value)| value.is_finite())
+    }
+}
+
+/// Samples that were measured, leaving out placeholders of a sweep cut short.
+fn measured(samples: &[(f32, f32)]) -> Vec<(f32, f32)> {
+    let mut samples: Vec<_> = samples.iter().copied().filter(|&(freq, _)| freq > 0.0).collect();
+    samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+    samples
+}
+
+/// |Γ| at `freq`, linear between the neighbouring samples, or none outside them.
+fn interpolate(samples: &[(f32, f32)], freq: f32) -> Option<f32> {
+    let i = samples.partition_point(|&(f, _)| f < freq);
+    match (i.checked_sub(1).map(|i| samples[i]), samples.get(i).copied()) {
+        (_, Some((f, value))) if f == freq => Some(value),
+        (Some((f0, v0)), Some((f1, v1))) => Some(v0 + (v1 - v0) * (freq - f0) / (f1 - f0)),
+        _ => None,
+    }
+}
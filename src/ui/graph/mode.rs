@@ -0,0 +1,80 @@
+use std::fmt::{Display, Formatter};
+
+use crate::protocol::measurement::Reflection;
+
+/// Quantity derived from the reflection coefficient that is plotted on the y axis.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum GraphMode {
+    #[default]
+    Swr,
+    ReturnLoss,
+    Reflection,
+    MismatchLoss,
+}
+
+impl GraphMode {
+    pub const ALL: [GraphMode; 4] = [
+        GraphMode::Swr,
+        GraphMode::ReturnLoss,
+        GraphMode::Reflection,
+        GraphMode::MismatchLoss,
+    ];
+
+    pub fn value(self, reflection: Reflection) -> f32 {
+        match self {
+            GraphMode::Swr => reflection.swr(),
+            GraphMode::ReturnLoss => reflection.return_loss(),
+            GraphMode::Reflection => reflection.magnitude(),
+            GraphMode::MismatchLoss => reflection.mismatch_loss(),
+        }
+    }
+
+    /// Default y axis range for this quantity.
+    pub fn range(self) -> (f32, f32) {
+        match self {
+            GraphMode::Swr => (1.0, 10.0),
+            GraphMode::ReturnLoss => (0.0, 40.0),
+            GraphMode::Reflection => (0.0, 1.0),
+            GraphMode::MismatchLoss => (0.0, 10.0),
+        }
+    }
+
+    pub fn axis_label(self) -> &'static str {
+        match self {
+            GraphMode::Swr => "SWR",
+            GraphMode::ReturnLoss => "Return loss [dB]",
+            GraphMode::Reflection => "|Γ|",
+            GraphMode::MismatchLoss => "Mismatch loss [dB]",
+        }
+    }
+
+    pub fn format_value(self, value: f32) -> String {
+        match self {
+            GraphMode::Swr => format!("SWR {:.2}:1", value),
+            GraphMode::ReturnLoss => format!("RL {:.2} dB", value),
+            GraphMode::Reflection => format!("|Γ| {:.3}", value),
+            GraphMode::MismatchLoss => format!("ML {:.2} dB", value),
+        }
+    }
+
+    /// Difference between two values, as shown for delta markers.
+    pub fn format_delta(self, delta: f32) -> String {
+        match self {
+            GraphMode::Swr => format!("ΔSWR {:+.2}", delta),
+            GraphMode::ReturnLoss => format!("ΔRL {:+.2} dB", delta),
+            GraphMode::Reflection => format!("Δ|Γ| {:+.3}", delta),
+            GraphMode::MismatchLoss => format!("ΔML {:+.2} dB", delta),
+        }
+    }
+}
+
+impl Display for GraphMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GraphMode::Swr => write!(f, "SWR"),
+            GraphMode::ReturnLoss => write!(f, "Return loss"),
+            GraphMode::Reflection => write!(f, "Reflection coefficient"),
+            GraphMode::MismatchLoss => write!(f, "Mismatch loss"),
+        }
+    }
+}
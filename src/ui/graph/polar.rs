@@ -0,0 +1,150 @@
+use std::f32::consts::PI;
+
+use plotters::coord::ReverseCoordTranslate;
+use plotters::prelude::*;
+use plotters_cairo::CairoBackend;
+use relm4::binding::Binding;
+use relm4::prelude::gtk::prelude::*;
+
+use crate::protocol::measurement::Reflection;
+use crate::ui::graph::analysis::{NARROW_SWR, WIDE_SWR};
+use crate::ui::graph::element::GraphElement;
+use crate::ui::graph::Graph;
+
+/// SWR of the grey circles, besides the bandwidth limits
+const SWR_CIRCLES: [f32; 3] = [3.0, 5.0, 10.0];
+/// Frequency labels around the rim
+const FREQ_TICKS: usize = 8;
+
+/// Point on the circle of radius `radius` at the share `t` of the frequency span, clockwise from the left.
+fn polar(radius: f32, t: f32) -> (f32, f32) {
+    let angle = PI - 2.0 * PI * t;
+    (radius * angle.cos(), radius * angle.sin())
+}
+
+/// Share of the frequency span at the angle of `(u, v)`, undoing [`polar`].
+fn share(u: f32, v: f32) -> f32 {
+    ((PI - v.atan2(u)) / (2.0 * PI)).rem_euclid(1.0)
+}
+
+fn circle(radius: f32) -> Vec<(f32, f32)> {
+    (0..=360).map(|i| polar(radius, i as f32 / 360.0)).collect()
+}
+
+impl Graph {
+    /// Draw |Γ| of the traces as a polar plot.
+    ///
+    /// The detector only measures |Γ|, so there is no phase to place the samples on a Smith chart.
+    /// Instead the distance from the centre is |Γ|, with circles of constant SWR, and the angle is the
+    /// frequency, running clockwise from the left over the frequency range of the view.
+    pub(super) fn draw_polar(&mut self) {
+        let size = self.draw_handler.drawing_area().allocation();
+        let w = size.width();
+        let h = size.height();
+        let cx = self.draw_handler.get_context();
+
+        let be = CairoBackend::new(&cx, (w as u32, h as u32)).expect("cairo issue");
+
+        let root = be.into_drawing_area();
+        root.fill(&WHITE).unwrap();
+
+        // Keep the plot round whatever the shape of the window
+        let side = (w.min(h) - 20).max(1) as u32;
+        let area = root.clone().shrink((((w as u32).saturating_sub(side)) / 2, ((h as u32).saturating_sub(side)) / 2), (side, side));
+        let mut chart = ChartBuilder::on(&area)
+            .build_cartesian_2d(-1.2f32..1.2f32, -1.2f32..1.2f32).unwrap();
+
+        let grid = BLACK.mix(0.2);
+        let plot = chart.plotting_area();
+        let font = ("sans-serif", 10).into_font().color(&BLACK.mix(0.5));
+        plot.draw(&PathElement::new(circle(1.0), BLACK)).unwrap();
+        for i in 0..FREQ_TICKS {
+            let t = i as f32 / FREQ_TICKS as f32;
+            plot.draw(&PathElement::new(vec![(0.0, 0.0), polar(1.0, t)], grid)).unwrap();
+            let freq = self.x_min + (self.x_max - self.x_min) * t;
+            let (u, v) = polar(1.1, t);
+            plot.draw(&Text::new(format!("{:.3}", freq / 1000000.0), (u - 0.08, v + 0.02), font.clone())).unwrap();
+        }
+        for swr in SWR_CIRCLES {
+            let radius = Reflection::from_swr(swr).magnitude();
+            plot.draw(&PathElement::new(circle(radius), grid)).unwrap();
+            plot.draw(&Text::new(format!("{}:1", swr), (0.0, radius), font.clone())).unwrap();
+        }
+        for swr in [NARROW_SWR, WIDE_SWR] {
+            let radius = Reflection::from_swr(swr).magnitude();
+            plot.draw(&PathElement::new(circle(radius), BLUE.mix(0.4))).unwrap();
+            let font = ("sans-serif", 10).into_font().color(&BLUE);
+            plot.draw(&Text::new(format!("{}:1", swr), (0.0, radius), font)).unwrap();
+        }
+
+        for elem in GraphElement::iter(&self.elements) {
+            let elem = elem.borrow();
+
+            if !elem.visible.get() { continue; }
+
+            let color = elem.color.get();
+            chart.draw_series(LineSeries::new(
+                self.polar_points(&elem).map(|(_, _, point)| point),
+                RGBColor(
+                    (color.red() * 255.0) as u8,
+                    (color.green() * 255.0) as u8,
+                    (color.blue() * 255.0) as u8,
+                ),
+            )).unwrap();
+        }
+
+        if let Some((u, v)) = self.pointer
+            .and_then(|(x, y)| chart.as_coord_spec().reverse_translate((x as i32, y as i32)))
+            .filter(|(u, v)| u.hypot(*v) <= 1.0) {
+            let reflection = Reflection::new(u.hypot(v));
+            let freq = self.x_min + (self.x_max - self.x_min) * share(u, v);
+            let mut readout = format!(
+                "{:.3} MHz, |Γ| {:.3}, SWR {:.2}:1",
+                freq / 1000000.0,
+                reflection.magnitude(),
+                reflection.swr(),
+            );
+            if let Some((freq, reflection)) = self.closest_polar((u, v)) {
+                readout.push_str(&format!(", nearest trace {:.3} MHz |Γ| {:.3} SWR {:.2}:1",
+                                          freq / 1000000.0,
+                                          reflection.magnitude(),
+                                          reflection.swr()));
+            }
+            chart.plotting_area().draw(&Cross::new((u, v), 6, BLACK)).unwrap();
+            root.draw_text(
+                &readout,
+                &("sans-serif", 10, &BLACK).into_text_style(&root),
+                (0, h - 10),
+            ).unwrap();
+        }
+
+        root.present().unwrap();
+        // Clicks can't be mapped onto the frequency axis in this view
+        self.coords = None;
+    }
+
+    /// Frequency and reflection of the trace sample drawn nearest to `point`.
+    fn closest_polar(&self, (u, v): (f32, f32)) -> Option<(f32, Reflection)> {
+        GraphElement::iter(&self.elements).filter_map(|elem| {
+            let elem = elem.borrow();
+
+            if !elem.visible.get() { return None; }
+
+            self.polar_points(&elem)
+                .map(|(freq, reflection, (pu, pv))| ((pu - u).hypot(pv - v), freq, reflection))
+                .min_by(|a, b| a.0.total_cmp(&b.0))
+        })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .filter(|(distance, _, _)| *distance < 0.05)
+            .map(|(_, freq, reflection)| (freq, Reflection::new(reflection)))
+    }
+
+    /// Measured samples within the frequency range of the view, with their position in the plot.
+    fn polar_points<'a>(&self, elem: &'a GraphElement) -> impl Iterator<Item=(f32, f32, (f32, f32))> + 'a {
+        let (x_min, x_max) = (self.x_min, self.x_max);
+        let span = (x_max - x_min).max(1.0);
+        elem.samples.iter()
+            .filter(move |(freq, _)| *freq > 0.0 && (x_min..=x_max).contains(freq))
+            .map(move |&(freq, reflection)| (freq, reflection, polar(reflection, (freq - x_min) / span)))
+    }
+}
@@ -0,0 +1,144 @@
+use std::fmt::{Display, Formatter};
+
+use log::error;
+use relm4::binding::Binding;
+use relm4::prelude::gtk::prelude::*;
+
+use crate::protocol::measurement::Reflection;
+use crate::ui::graph::element::GraphElement;
+use crate::ui::graph::mode::GraphMode;
+use crate::ui::graph::Graph;
+
+/// Share of the fitted range added above and below the traces
+const FIT_MARGIN: f32 = 0.05;
+
+/// How the limits of the y axis are chosen.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum Scale {
+    /// Fixed range of the plotted quantity
+    #[default]
+    Default,
+    /// Follow all visible traces as they are measured
+    FitVisible,
+    /// Follow the trace being measured
+    FitActive,
+    /// Limits typed in by the user
+    Manual,
+}
+
+impl Scale {
+    pub const ALL: [Scale; 4] = [Scale::Default, Scale::FitVisible, Scale::FitActive, Scale::Manual];
+
+    pub fn fits(self) -> bool {
+        matches!(self, Scale::FitVisible | Scale::FitActive)
+    }
+}
+
+impl Display for Scale {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Scale::Default => write!(f, "Default"),
+            Scale::FitVisible => write!(f, "Fit visible traces"),
+            Scale::FitActive => write!(f, "Fit active trace"),
+            Scale::Manual => write!(f, "Manual"),
+        }
+    }
+}
+
+impl Graph {
+    /// Whether the y axis is logarithmic, which only makes sense for SWR itself.
+    pub(super) fn log_axis(&self) -> bool {
+        self.log_scale && self.mode == GraphMode::Swr && !self.derived_visible()
+    }
+
+    /// Whether differences or ratios of traces are shown, which can be below one or negative.
+    pub(super) fn derived_visible(&self) -> bool {
+        GraphElement::iter(&self.elements).any(|elem| {
+            let elem = elem.borrow();
+            elem.visible.get() && elem.derivation.is_some()
+        })
+    }
+
+    /// Scale in effect, fitting derived traces the default range of the quantity would miss.
+    pub(super) fn scale(&self) -> Scale {
+        match self.scale {
+            Scale::Default if self.derived_visible() => Scale::FitVisible,
+            scale => scale,
+        }
+    }
+
+    /// Position of `value` along the y axis of the chart.
+    pub(super) fn axis(&self, value: f32) -> f32 {
+        match self.log_axis() {
+            true => value.max(1.0).log10(),
+            false => value,
+        }
+    }
+
+    /// Value at position `axis` along the y axis of the chart.
+    pub(super) fn value(&self, axis: f32) -> f32 {
+        match self.log_axis() {
+            true => 10f32.powf(axis),
+            false => axis,
+        }
+    }
+
+    /// Set the y axis to the limits of the current scale, keeping it as it is when they are unknown.
+    pub(super) fn apply_scale(&mut self) {
+        let limits = match self.scale() {
+            Scale::Default => Some(self.mode.range()),
+            Scale::FitVisible | Scale::FitActive => self.fit(),
+            Scale::Manual => self.manual_limits(),
+        };
+        if let Some((y_min, y_max)) = limits {
+            (self.y_min, self.y_max) = (y_min, y_max);
+            (self.home.y_min, self.home.y_max) = (y_min, y_max);
+        }
+    }
+
+    /// Range of the traces followed by the scale, with a little room around them.
+    fn fit(&self) -> Option<(f32, f32)> {
+        let active = self.active.and_then(|index| self.elements.get(index));
+        let elements: Vec<_> = match self.scale() {
+            Scale::FitActive => active.into_iter().collect(),
+            _ => GraphElement::iter(&self.elements).collect(),
+        };
+        let (_, default_max) = self.mode.range();
+        let (low, high) = elements.iter()
+            .map(|elem| elem.borrow())
+            .filter(|elem| elem.visible.get())
+            .flat_map(|elem| match elem.derivation {
+                // Derived values don't follow |Γ|, so all of them have to be looked at
+                Some(_) => elem.points(self.mode).map(|(_, value)| value).collect(),
+                // Extents are in |Γ|, which every quantity grows or shrinks with
+                None if elem.y_min.get() <= elem.y_max.get() => [elem.y_min.get(), elem.y_max.get()].iter()
+                    .map(|&reflection| self.mode.value(Reflection::new(reflection)))
+                    .map(|value| if value.is_finite() { value } else { default_max })
+                    .collect(),
+                None => vec![],
+            })
+            .fold(None, |range: Option<(f32, f32)>, value| match range {
+                Some((low, high)) => Some((low.min(value), high.max(value))),
+                None => Some((value, value)),
+            })?;
+
+        let (low, high) = (self.axis(low), self.axis(high));
+        let margin = match high - low {
+            span if span > 0.0 => span * FIT_MARGIN,
+            _ => (high.abs() * FIT_MARGIN).max(0.01),
+        };
+        Some((self.value(low - margin), self.value(high + margin)))
+    }
+
+    /// Limits from the entries, if they make a valid range.
+    fn manual_limits(&self) -> Option<(f32, f32)> {
+        let parse = |buffer: &gtk4::EntryBuffer| buffer.text().trim().parse::<f32>().ok().filter(|v| v.is_finite());
+        match (parse(&self.manual_min), parse(&self.manual_max)) {
+            (Some(y_min), Some(y_max)) if y_min < y_max => Some((y_min, y_max)),
+            _ => {
+                error!("Invalid y axis limits, the minimum must be a number below the maximum");
+                None
+            }
+        }
+    }
+}
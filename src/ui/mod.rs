@@ -1,24 +1,44 @@
 use std::fmt::Debug;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+use std::sync::Arc;
 
-use ::log::error;
+use ::log::{error, info, warn};
 use gtk4::glib::Propagation;
 use relm4::{Component, ComponentController, Controller, gtk, WorkerController};
+use relm4::actions::{AccelsPlus, RelmAction, RelmActionGroup};
 use relm4::prelude::*;
 use relm4::prelude::gtk::prelude::*;
 
-use crate::protocol::SweepParams;
+use crate::format::session;
+use crate::format::session::Session;
+use crate::protocol::calibration::Calibration;
+use crate::protocol::Connection;
+use crate::protocol::plan::SweepPlan;
 use crate::try_install_udev;
+use crate::ui::calibration::CalibrationWizard;
 use crate::ui::controls::Controls;
+use crate::ui::dummy::DummyDialog;
 use crate::ui::graph::Graph;
 use crate::ui::log::LogWindow;
+use crate::ui::sample_buffer::SampleBuffer;
+use crate::ui::status::SweepStatus;
 use crate::ui::swr_worker::{State, SwrWorker};
+use crate::ui::util::choose_file;
 
+mod calibration;
 mod controls;
+mod dummy;
 mod graph;
 mod log;
+mod sample_buffer;
+mod settings;
+mod status;
 mod swr_worker;
 mod util;
 
+const SESSION_FILTER: (&str, &str) = ("Session (*.swrs)", "*.swrs");
 
 pub struct App {
     analyzer: WorkerController<SwrWorker>,
@@ -26,6 +46,15 @@ pub struct App {
     graph: Controller<Graph>,
     state: State,
     log_window: Controller<LogWindow>,
+    calibration_wizard: Controller<CalibrationWizard>,
+    dummy_dialog: Controller<DummyDialog>,
+    calibration: Option<Arc<Calibration>>,
+    calibrating: bool,
+    /// Samples on their way from the sweep thread
+    samples: SampleBuffer,
+    /// How far the sweep running or last run got
+    sweep: Option<SweepStatus>,
+    device_version: Option<String>,
 }
 
 #[derive(Debug)]
@@ -36,12 +65,28 @@ pub enum Input {
     #[doc(hidden)]
     #[allow(private_interfaces)]
     Worker(swr_worker::Output),
+    #[doc(hidden)]
+    #[allow(private_interfaces)]
+    Calibration(calibration::Output),
+    #[doc(hidden)]
+    #[allow(private_interfaces)]
+    Graph(graph::Output),
+    #[doc(hidden)]
+    #[allow(private_interfaces)]
+    Dummy(dummy::Output),
     ToggleLog,
+    OpenSession,
+    SaveSession,
+    LoadSessionFile(PathBuf),
+    SaveSessionFile(PathBuf),
     #[doc(hidden)]
     #[allow(private_interfaces)]
     StateChange(State),
 }
 
+relm4::new_action_group!(WindowActionGroup, "win");
+relm4::new_stateless_action!(OpenSessionAction, WindowActionGroup, "open-session");
+relm4::new_stateless_action!(SaveSessionAction, WindowActionGroup, "save-session");
 
 #[relm4::component(pub)]
 //noinspection RsSortImplTraitMembers
@@ -57,6 +102,13 @@ impl Component for App {
             set_title: Some("SWR analyzer"),
             set_default_size: (800, 600),
             set_size_request: (800, 600),
+            #[wrap(Some)]
+            set_titlebar = &gtk::HeaderBar {
+                pack_start = &gtk::MenuButton {
+                    set_label: "File",
+                    set_menu_model: Some(&file_menu),
+                },
+            },
 
             gtk::Grid {
                 attach[0, 0, 1, 1]= model.controls.widget(),
@@ -67,9 +119,22 @@ impl Component for App {
                         sender.input(Input::ToggleLog)
                     }
                 },
-                attach[1, 1, 1, 1]= &gtk::Label {
-                    #[watch]
-                    set_label: &model.state.to_string(),
+                attach[1, 1, 1, 1]= &gtk::Box {
+                    set_orientation: gtk::Orientation::Horizontal,
+                    set_spacing: 8,
+
+                    gtk::Label {
+                        #[watch]
+                        set_label: &model.status(),
+                    },
+                    gtk::ProgressBar {
+                        set_hexpand: true,
+                        set_valign: gtk::Align::Center,
+                        #[watch]
+                        set_visible: model.state == State::Busy,
+                        #[watch]
+                        set_fraction: model.sweep.as_ref().map_or(0.0, SweepStatus::fraction),
+                    },
                 },
             },
             
@@ -83,46 +148,161 @@ impl Component for App {
         }
     }
 
-    fn update(&mut self, message: Self::Input, _sender: ComponentSender<Self>, _root: &Self::Root) {
+    menu! {
+        file_menu: {
+            "Open session" => OpenSessionAction,
+            "Save session" => SaveSessionAction,
+        }
+    }
+
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>, root: &Self::Root) {
         match message {
-            Input::Controls(controls::Output::Connect { dummy }) => {
-                self.analyzer.emit(swr_worker::Input::Connect { dummy });
+            Input::Controls(controls::Output::Connect(connection)) => {
+                self.analyzer.emit(swr_worker::Input::Connect(connection));
+            }
+            Input::Controls(controls::Output::ConfigureDummy) => {
+                self.dummy_dialog.emit(dummy::Input::Open);
+            }
+            Input::Controls(controls::Output::Bands(bands)) => {
+                self.graph.emit(graph::Input::SetBands(bands));
+            }
+            Input::Graph(graph::Output::SweepRange { start_freq, stop_freq }) => {
+                self.controls.emit(controls::Input::SweepRange {
+                    start_freq: start_freq.round() as i32,
+                    stop_freq: stop_freq.round() as i32,
+                });
+            }
+            Input::Dummy(dummy::Output::Connect(model)) => {
+                self.analyzer.emit(swr_worker::Input::Connect(Connection::Dummy(model)));
             }
             Input::Controls(controls::Output::Disconnect) => {
                 self.analyzer.emit(swr_worker::Input::Disconnect);
             }
-            Input::Controls(controls::Output::Start { continuous, start_freq, stop_freq, step_count, step_millis }) => {
+            Input::Controls(controls::Output::Enumerate) => {
+                self.analyzer.emit(swr_worker::Input::Enumerate);
+            }
+            Input::Controls(controls::Output::Start { continuous, start_freq, stop_freq, step_count, step_millis, noise_filter }) => {
+                let plan = SweepPlan::new(start_freq, stop_freq, step_count, step_millis, noise_filter);
+                // Whatever is left of the last sweep belongs to its trace
+                self.deliver_samples();
+
                 self.graph.sender().emit(graph::Input::Clear {
                     x_min: start_freq as f32,
                     x_max: stop_freq as f32,
-                    y_min: 0.0,
-                    y_max: 1000.0,
+                    params: plan.params(),
+                    device: self.device_version.clone(),
                 });
 
-                let step_freq = (stop_freq - start_freq) / step_count + 1;
-                let params = SweepParams {
-                    noise_filter: 600,
+                let calibration = self.calibration.clone()
+                    .filter(|c| c.matches(start_freq, stop_freq, step_count));
+                if calibration.is_none() && self.calibration.is_some() {
+                    warn!("calibration does not match the sweep range, measuring uncalibrated");
+                }
+
+                self.start_sweep(plan.clone(), continuous);
+                self.analyzer.emit(swr_worker::Input::Start {
+                    continuous,
+                    plan,
+                    calibration,
+                });
+            }
+            Input::Controls(controls::Output::Calibrate { start_freq, stop_freq, step_count, step_millis, noise_filter }) => {
+                self.calibration_wizard.emit(calibration::Input::Open {
                     start_freq,
-                    step_freq,
+                    stop_freq,
                     step_count,
                     step_millis,
+                    noise_filter,
+                });
+            }
+            Input::Controls(controls::Output::LoadCalibration(path)) => {
+                self.calibration = None;
+                let Some(path) = path else {
+                    return;
                 };
-
+                match Calibration::load(&path) {
+                    Ok(calibration) => {
+                        info!("loaded calibration {}", path.display());
+                        self.calibration = Some(Arc::new(calibration));
+                    }
+                    Err(e) => error!("loading calibration {}: {}", path.display(), e),
+                }
+            }
+            Input::Controls(controls::Output::Cancel) => {
+                self.analyzer.emit(swr_worker::Input::Cancel);
+            }
+            Input::Worker(swr_worker::Output::Connected { version }) => {
+                self.device_version = Some(version);
+            }
+            Input::Worker(swr_worker::Output::Devices(devices)) => {
+                self.controls.emit(controls::Input::Devices(devices));
+            }
+            Input::Worker(swr_worker::Output::SamplesReady) => {
+                self.deliver_samples();
+            }
+            Input::Calibration(calibration::Output::Sweep(plan)) => {
+                self.deliver_samples();
+                self.calibrating = true;
+                self.start_sweep(plan.clone(), false);
                 self.analyzer.emit(swr_worker::Input::Start {
-                    continuous,
-                    params,
+                    continuous: false,
+                    plan,
+                    calibration: None,
                 });
             }
-            Input::Controls(controls::Output::Cancel) => {
+            Input::Calibration(calibration::Output::Cancel) => {
                 self.analyzer.emit(swr_worker::Input::Cancel);
             }
-            Input::Worker(swr_worker::Output::Sample(sample)) => {
-                self.graph.emit(graph::Input::Sample(sample));
+            Input::Calibration(calibration::Output::Saved { path, calibration }) => {
+                self.calibration = Some(calibration);
+                self.controls.emit(controls::Input::CalibrationSaved(path));
+            }
+            Input::StateChange(state) => {
+                if state != State::Busy && self.calibrating {
+                    // The end of the sweep can overtake the message about its last samples
+                    self.deliver_samples();
+                    self.calibrating = false;
+                    self.calibration_wizard.emit(calibration::Input::SweepDone);
+                }
+                self.state = state;
             }
-            Input::StateChange(state) => { self.state = state; }
             Input::ToggleLog => {
                 self.log_window.emit(log::Input::ToggleVisible);
             }
+            Input::OpenSession => {
+                choose_file(root.upcast_ref(), "Open session", gtk::FileChooserAction::Open, SESSION_FILTER,
+                            move |path| sender.input(Input::LoadSessionFile(path)));
+            }
+            Input::SaveSession => {
+                choose_file(root.upcast_ref(), "Save session", gtk::FileChooserAction::Save, SESSION_FILTER,
+                            move |path| sender.input(Input::SaveSessionFile(path)));
+            }
+            Input::LoadSessionFile(path) => {
+                if self.state == State::Busy {
+                    error!("stop the sweep before opening a session");
+                    return;
+                }
+                match File::open(&path).map_err(Into::into).and_then(|f| session::read(BufReader::new(f))) {
+                    Ok(Session { controls, view, traces }) => {
+                        self.controls.emit(controls::Input::Restore(controls));
+                        self.graph.emit(graph::Input::Restore { view, traces });
+                        info!("opened session {}", path.display());
+                    }
+                    Err(e) => error!("opening session {}: {}", path.display(), e),
+                }
+            }
+            Input::SaveSessionFile(path) => {
+                let (view, traces) = self.graph.model().session();
+                let session = Session {
+                    controls: self.controls.model().entries(),
+                    view,
+                    traces,
+                };
+                match File::create(&path).and_then(|f| session::write(BufWriter::new(f), &session)) {
+                    Ok(()) => info!("saved session to {}", path.display()),
+                    Err(e) => error!("saving session {}: {}", path.display(), e),
+                }
+            }
             Input::Controls(controls::Output::Udev) => {
                 if let Err(e) = try_install_udev(true) {
                     error!("error installing udev rules: {}", e);
@@ -137,14 +317,22 @@ impl Component for App {
             .forward(sender.input_sender(), Input::Controls);
         let graph = Graph::builder()
             .launch(root.clone().into())
-            .detach();
+            .forward(sender.input_sender(), Input::Graph);
         let log_window = LogWindow::builder()
             .launch(())
             .detach();
+        let calibration_wizard = CalibrationWizard::builder()
+            .launch(root.clone().into())
+            .forward(sender.input_sender(), Input::Calibration);
+        let dummy_dialog = DummyDialog::builder()
+            .launch(root.clone().into())
+            .forward(sender.input_sender(), Input::Dummy);
 
+        let samples = SampleBuffer::default();
         let analyzer = SwrWorker::builder()
-            .detach_worker(())
+            .detach_worker(samples.clone())
             .forward(sender.input_sender(), Input::Worker);
+        analyzer.emit(swr_worker::Input::Enumerate);
 
         let model = Self {
             state: State::Disconnected,
@@ -152,12 +340,67 @@ impl Component for App {
             controls,
             graph,
             log_window,
+            calibration_wizard,
+            dummy_dialog,
+            calibration: None,
+            calibrating: false,
+            samples,
+            sweep: None,
+            device_version: None,
         };
 
         let widgets = view_output!();
 
+        let open_session: RelmAction<OpenSessionAction> = {
+            let sender = sender.clone();
+            RelmAction::new_stateless(move |_| sender.input(Input::OpenSession))
+        };
+        let save_session: RelmAction<SaveSessionAction> = {
+            let sender = sender.clone();
+            RelmAction::new_stateless(move |_| sender.input(Input::SaveSession))
+        };
+        let mut actions = RelmActionGroup::<WindowActionGroup>::new();
+        actions.add_action(open_session);
+        actions.add_action(save_session);
+        actions.register_for_widget(&root);
+
+        let app = relm4::main_application();
+        app.set_accelerators_for_action::<OpenSessionAction>(&["<primary>O"]);
+        app.set_accelerators_for_action::<SaveSessionAction>(&["<primary>S"]);
+
         swr_worker::STATE.subscribe(sender.input_sender(), |state| Input::StateChange(*state));
 
         ComponentParts { model, widgets }
     }
 }
+
+impl App {
+    fn start_sweep(&mut self, plan: SweepPlan, continuous: bool) {
+        self.sweep = Some(SweepStatus::new(plan, continuous));
+    }
+
+    /// Pass the samples measured so far to whoever is sweeping.
+    fn deliver_samples(&mut self) {
+        let samples = self.samples.drain();
+        if samples.is_empty() {
+            return;
+        }
+        if let Some(sweep) = &mut self.sweep {
+            samples.iter().for_each(|sample| sweep.record(sample.index));
+        }
+        if self.calibrating {
+            for sample in samples {
+                self.calibration_wizard.emit(calibration::Input::Sample(sample));
+            }
+        } else {
+            self.graph.emit(graph::Input::Samples(samples));
+        }
+    }
+
+    fn status(&self) -> String {
+        match &self.sweep {
+            Some(sweep) => sweep.describe(self.state),
+            None => self.state.to_string(),
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::ui::swr_worker::Sample;
+
+/// Samples the UI may fall behind by before the sweep waits for it
+const CAPACITY: usize = 4096;
+/// How often a waiting sweep looks for a cancel
+const CANCEL_POLL: Duration = Duration::from_millis(50);
+
+/// Bounded queue carrying samples from the sweep thread to the UI.
+///
+/// Instead of one main loop message per sample, the sweep thread only wakes the UI when the queue
+/// was empty, and the UI takes everything queued at once. When the UI falls behind, the sweep thread
+/// waits before acknowledging the next sample, which slows the analyzer down.
+#[derive(Clone, Default)]
+pub(super) struct SampleBuffer {
+    shared: Arc<(Mutex<VecDeque<Sample>>, Condvar)>,
+}
+
+impl SampleBuffer {
+    /// Queue `sample`, waiting for room unless `cancel` is set.
+    ///
+    /// Returns true if the queue was empty, so the UI has to be told there are samples to take.
+    pub(super) fn push(&self, sample: Sample, cancel: &AtomicBool) -> bool {
+        let (queue, room) = &*self.shared;
+        let mut queue = queue.lock().unwrap();
+        while queue.len() >= CAPACITY {
+            if cancel.load(Ordering::Relaxed) {
+                // The sweep is ending anyway, nobody waits for this sample
+                return false;
+            }
+            queue = room.wait_timeout(queue, CANCEL_POLL).unwrap().0;
+        }
+        queue.push_back(sample);
+        queue.len() == 1
+    }
+
+    /// Take every queued sample.
+    pub(super) fn drain(&self) -> Vec<Sample> {
+        let (queue, room) = &*self.shared;
+        let samples = queue.lock().unwrap().drain(..).collect();
+        room.notify_all();
+        samples
+    }
+}
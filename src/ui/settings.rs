@@ -0,0 +1,49 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use gtk4::glib;
+use log::error;
+
+/// File with the choices remembered between runs, one `key=value` per line.
+fn settings_path() -> PathBuf {
+    glib::user_config_dir().join("swr-analyzer").join("settings")
+}
+
+fn read_all() -> Vec<(String, String)> {
+    let Ok(contents) = fs::read_to_string(settings_path()) else {
+        return vec![];
+    };
+    contents.lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+pub(super) fn get(key: &str) -> Option<String> {
+    read_all().into_iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, value)| value)
+}
+
+pub(super) fn set(key: &str, value: &str) {
+    let mut settings = read_all();
+    match settings.iter_mut().find(|(k, _)| k == key) {
+        Some((_, v)) => *v = value.to_string(),
+        None => settings.push((key.to_string(), value.to_string())),
+    }
+    if let Err(e) = write_all(&settings) {
+        error!("saving settings: {}", e);
+    }
+}
+
+fn write_all(settings: &[(String, String)]) -> io::Result<()> {
+    let path = settings_path();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let contents: String = settings.iter()
+        .map(|(key, value)| format!("{}={}\n", key, value))
+        .collect();
+    fs::write(path, contents)
+}
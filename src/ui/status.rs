@@ -0,0 +1,88 @@
+use std::time::{Duration, Instant};
+
+use crate::protocol::plan::{Progress, SweepPlan};
+use crate::ui::swr_worker::State;
+
+/// How far the running sweep got, for the status row.
+pub(super) struct SweepStatus {
+    plan: SweepPlan,
+    continuous: bool,
+    progress: Option<Progress>,
+    last_index: Option<usize>,
+    /// Start of the pass being measured
+    pass_started: Instant,
+    /// Passes completed in continuous mode
+    passes: usize,
+    last_pass: Option<Duration>,
+}
+
+impl SweepStatus {
+    pub(super) fn new(plan: SweepPlan, continuous: bool) -> Self {
+        Self {
+            plan,
+            continuous,
+            progress: None,
+            last_index: None,
+            pass_started: Instant::now(),
+            passes: 0,
+            last_pass: None,
+        }
+    }
+
+    /// Count the sample at `index`, noticing when a continuous sweep starts over.
+    pub(super) fn record(&mut self, index: usize) {
+        if self.last_index.is_some_and(|last| index <= last) {
+            let now = Instant::now();
+            self.passes += 1;
+            self.last_pass = Some(now - self.pass_started);
+            self.pass_started = now;
+        }
+        self.last_index = Some(index);
+        self.progress = Some(self.plan.progress(index));
+    }
+
+    /// Share of the current pass measured, from 0 to 1.
+    pub(super) fn fraction(&self) -> f64 {
+        self.progress.map_or(0.0, |p| p.points as f64 / p.total as f64)
+    }
+
+    /// Time left in the current pass, from the pace so far or the step time before the first sample.
+    fn remaining(&self) -> Duration {
+        match self.progress {
+            Some(Progress { points, total, .. }) => {
+                self.pass_started.elapsed().mul_f64((total - points) as f64 / points as f64)
+            }
+            None => {
+                let step = Duration::from_millis(self.plan.params().step_millis.max(0) as u64);
+                step * self.plan.point_count() as u32
+            }
+        }
+    }
+
+    pub(super) fn describe(&self, state: State) -> String {
+        let mut status = state.to_string();
+        if state != State::Busy {
+            return status;
+        }
+        if let Some(Progress { segment, segments, .. }) = self.progress.filter(|p| p.segments > 1) {
+            status.push_str(&format!(", segment {} of {}", segment, segments));
+        }
+        status.push_str(&format!(", {} left", format_duration(self.remaining())));
+        if self.continuous {
+            status.push_str(&format!(", sweep {}", self.passes + 1));
+            if let Some(last_pass) = self.last_pass {
+                status.push_str(&format!(", last took {}", format_duration(last_pass)));
+            }
+        }
+        status
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs_f64();
+    if seconds < 60.0 {
+        format!("{:.1} s", seconds)
+    } else {
+        format!("{}:{:02} min", duration.as_secs() / 60, duration.as_secs() % 60)
+    }
+}
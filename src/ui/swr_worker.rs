@@ -5,46 +5,67 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
 use std::time::Duration;
 
-use log::{error, info};
+use log::{error, info, warn};
 use relm4::{Component, ComponentParts, ComponentSender, Sender, SharedState};
 
-use crate::protocol::{error, SweepParams, SWRAnalyzer};
-use crate::protocol::dummy::Dummy;
-use crate::protocol::foxdelta::FoxDeltaAnalyzer;
-use crate::protocol::libusb::SerialHID;
+use crate::protocol::{connect, Connection, SWRAnalyzer};
+use crate::protocol::error::Error;
+use crate::protocol::libusb::{DeviceId, DeviceInfo, enumerate, HotplugEvent, locate, watch_hotplug};
+use crate::protocol::calibration::Calibration;
+use crate::protocol::plan::SweepPlan;
+use crate::protocol::measurement::{Detector, Reflection};
+use crate::ui::sample_buffer::SampleBuffer;
 
 pub(super) static STATE: SharedState<State> = SharedState::new();
 
+/// Time a replugged device needs before it answers
+const SETTLE_TIME: Duration = Duration::from_millis(500);
+
 #[derive(Debug)]
 pub(super) enum Input {
-    Connect { dummy: bool },
+    Connect(Connection),
     Disconnect,
+    Enumerate,
+    Hotplug(HotplugEvent),
     Start {
         continuous: bool,
-        params: SweepParams,
+        plan: SweepPlan,
+        calibration: Option<Arc<Calibration>>,
     },
     Cancel,
 }
 
 #[derive(Debug)]
 pub(super) enum Output {
-    Sample(Sample),
+    Connected { version: String },
+    Devices(Vec<DeviceInfo>),
+    /// Samples are waiting in the buffer
+    SamplesReady,
 }
 
 pub(super) struct SwrWorker {
     device: InternalState<Box<dyn SWRAnalyzer + Send>>,
+    /// Last connection made, kept after an unplug to reconnect when the device returns
+    connection: Option<Connection>,
+    /// Bus address of the connected USB device
+    port: Option<DeviceId>,
+    /// The device was unplugged during a sweep and must be dropped when it ends
+    unplugged: bool,
+    samples: SampleBuffer,
 }
 
 pub(super) enum CommandOutput {
-    Sample(Sample),
+    SamplesReady,
     Done(Box<dyn SWRAnalyzer + Send>),
+    Unplugged,
 }
 
 impl Debug for CommandOutput {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             CommandOutput::Done(_) => write!(f, "Done"),
-            CommandOutput::Sample(sample) => write!(f, "Sample({:?})", sample),
+            CommandOutput::Unplugged => write!(f, "Unplugged"),
+            CommandOutput::SamplesReady => write!(f, "SamplesReady"),
         }
     }
 }
@@ -54,18 +75,27 @@ impl Component for SwrWorker {
     type Input = Input;
     type Output = Output;
 
-    type Init = ();
+    type Init = SampleBuffer;
     type Root = ();
     type Widgets = ();
 
     fn init_root() -> Self::Root {}
 
-    fn init(_init: Self::Init, _root: Self::Root, _sender: ComponentSender<Self>) -> relm4::ComponentParts<SwrWorker> {
+    fn init(samples: Self::Init, _root: Self::Root, sender: ComponentSender<Self>) -> relm4::ComponentParts<SwrWorker> {
         *STATE.write() = State::Disconnected;
         let model = Self {
             device: InternalState::Disconnected,
+            connection: None,
+            port: None,
+            unplugged: false,
+            samples,
         };
 
+        let input = sender.input_sender().clone();
+        if let Err(e) = watch_hotplug(move |event| input.emit(Input::Hotplug(event))) {
+            warn!("hotplug detection unavailable, unplugging will not be noticed: {}", e);
+        }
+
         ComponentParts {
             model,
             widgets: (),
@@ -74,21 +104,21 @@ impl Component for SwrWorker {
 
     fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>, _root: &Self::Root) {
         match message {
-            Input::Connect { dummy } => {
+            Input::Connect(connection) => {
                 if !matches!(self.device, InternalState::Disconnected) {
                     error!("already connected");
                     return;
                 }
-                match get_analyzer(dummy) {
-                    Ok(device) => {
-                        self.device = InternalState::Idle(device)
-                    }
-                    Err(e) => {
-                        error!("connecting: {}", e);
-                        return;
-                    }
+                match self.connect(connection) {
+                    Ok(version) => sender.output(Output::Connected { version }).unwrap(),
+                    Err(e) => error!("connecting: {}", e),
+                }
+            }
+            Input::Enumerate => {
+                match enumerate() {
+                    Ok(devices) => sender.output(Output::Devices(devices)).unwrap(),
+                    Err(e) => error!("listing devices: {}", e),
                 }
-                *STATE.write() = State::Idle;
             }
             Input::Disconnect => {
                 if !matches!(self.device, InternalState::Idle(..)) {
@@ -96,22 +126,37 @@ impl Component for SwrWorker {
                     return;
                 }
                 self.device = InternalState::Disconnected;
+                self.connection = None;
+                self.port = None;
                 *STATE.write() = State::Disconnected;
             }
-            Input::Start { continuous, params } => {
+            Input::Hotplug(event) => {
+                if let Some(version) = self.hotplug(event) {
+                    sender.output(Output::Connected { version }).unwrap();
+                }
+                sender.input(Input::Enumerate);
+            }
+            Input::Start { continuous, plan, calibration } => {
                 *STATE.write() = State::Busy;
                 let cancel = Arc::new(AtomicBool::new(false));
                 let Some(mut device) = self.device.take(cancel.clone()) else {
                     error!("device not available");
                     return;
                 };
+                let samples = self.samples.clone();
                 sender.spawn_command(move |sender| {
                     let mut handler = |i, freq, sample| {
-                        sender.send(CommandOutput::Sample(Sample {
+                        let detector = calibration.as_ref()
+                            .map_or_else(Detector::default, |c| c.detector(i as usize));
+                        let sample = Sample {
                             index: i as usize,
                             freq: freq as f32,
                             value: sample as f32,
-                        })).expect("output hung up");
+                            reflection: detector.reflection(sample as f32),
+                        };
+                        if samples.push(sample, &cancel) {
+                            sender.send(CommandOutput::SamplesReady).expect("output hung up");
+                        }
 
                         if cancel.load(Ordering::Relaxed) {
                             ControlFlow::Break(())
@@ -119,14 +164,13 @@ impl Component for SwrWorker {
                             ControlFlow::Continue(())
                         }
                     };
-                    if let Err(e) =
-                        device.start_sweep(
-                            continuous,
-                            params,
-                            &mut handler,
-                        )
-                    {
-                        error!("error during sweep: {}", e);
+                    match plan.run(device.as_mut(), continuous, &mut handler) {
+                        Err(Error::LibUsb(rusb::Error::NoDevice)) => {
+                            sender.send(CommandOutput::Unplugged).unwrap();
+                            return;
+                        }
+                        Err(e) => error!("error during sweep: {}", e),
+                        Ok(()) => {}
                     }
 
                     sender.send(CommandOutput::Done(device)).unwrap()
@@ -144,13 +188,21 @@ impl Component for SwrWorker {
 
     fn update_cmd(&mut self, message: Self::CommandOutput, sender: ComponentSender<Self>, _root: &Self::Root) {
         match message {
-            CommandOutput::Sample(s) => {
-                sender.output(Output::Sample(s)).unwrap()
+            CommandOutput::SamplesReady => {
+                sender.output(Output::SamplesReady).unwrap()
             }
             CommandOutput::Done(device) => {
+                if self.unplugged {
+                    self.lost();
+                    return;
+                }
                 self.device = InternalState::Idle(device);
                 *STATE.write() = State::Idle;
             }
+            CommandOutput::Unplugged => {
+                warn!("analyzer unplugged during sweep");
+                self.lost();
+            }
         }
     }
 
@@ -164,36 +216,90 @@ impl Component for SwrWorker {
     }
 }
 
-fn get_analyzer(use_dummy: bool) -> error::Result<Box<dyn SWRAnalyzer + Send>> {
-    let mut device: Box<dyn SWRAnalyzer + Send> = if use_dummy {
-        Box::new(Dummy)
-    } else {
-        Box::new(FoxDeltaAnalyzer::from(SerialHID::new()?))
-    };
-    info!("version: {}", device.version()?);
-    Ok(device)
+impl SwrWorker {
+    /// Open `connection`, returning the firmware version.
+    fn connect(&mut self, connection: Connection) -> crate::protocol::error::Result<String> {
+        // Pin USB devices to their bus address, so an unplug can be matched to the open device
+        let port = match &connection {
+            Connection::FoxDelta(id) => Some(locate(id.as_ref())?),
+            _ => None,
+        };
+        let (device, version) = match &port {
+            Some(port) => connect(&Connection::FoxDelta(Some(port.clone())))?,
+            None => connect(&connection)?,
+        };
+        self.device = InternalState::Idle(device);
+        self.connection = Some(connection);
+        self.port = port;
+        self.unplugged = false;
+        *STATE.write() = State::Idle;
+        Ok(version)
+    }
+
+    /// Follow a device being plugged in or removed, returning the version if the analyzer was reconnected.
+    fn hotplug(&mut self, event: HotplugEvent) -> Option<String> {
+        match event {
+            HotplugEvent::Left { bus, address } => {
+                if self.port != Some(DeviceId::Port { bus, address }) {
+                    return None;
+                }
+                warn!("analyzer unplugged");
+                match &self.device {
+                    InternalState::Busy { cancel } => {
+                        cancel.store(true, Ordering::Relaxed);
+                        self.unplugged = true;
+                    }
+                    _ => self.lost(),
+                }
+            }
+            HotplugEvent::Arrived { .. } => {
+                // Any USB device arriving ends up here, leave a connected analyzer alone
+                if !matches!(self.device, InternalState::Disconnected) {
+                    return None;
+                }
+                let connection = self.connection.take()?;
+                thread::sleep(SETTLE_TIME);
+                match self.connect(connection.clone()) {
+                    Ok(version) => {
+                        info!("analyzer reconnected");
+                        return Some(version);
+                    }
+                    Err(e) => {
+                        warn!("reconnecting: {}", e);
+                        self.connection = Some(connection);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Forget the unplugged device, but keep the connection to restore it when it comes back.
+    fn lost(&mut self) {
+        self.device = InternalState::Disconnected;
+        self.port = None;
+        self.unplugged = false;
+        *STATE.write() = State::Disconnected;
+    }
 }
 
-#[derive(Debug)]
+#[derive(Copy, Clone, Debug)]
 pub struct Sample {
     pub index: usize,
     pub freq: f32,
+    /// Raw detector reading
     pub value: f32,
+    pub reflection: Reflection,
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
 pub(super) enum State {
+    #[default]
     Disconnected,
     Idle,
     Busy,
 }
 
-impl Default for State {
-    fn default() -> Self {
-        Self::Disconnected
-    }
-}
-
 impl Display for State {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -220,4 +326,49 @@ impl<T> InternalState<T> {
             }
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::dummy::AntennaModel;
+
+    fn connected() -> SwrWorker {
+        let connection = Connection::Dummy(AntennaModel::default());
+        let (device, _) = connect(&connection).unwrap();
+        SwrWorker {
+            device: InternalState::Idle(device),
+            connection: Some(connection),
+            port: Some(DeviceId::Port { bus: 1, address: 2 }),
+            unplugged: false,
+            samples: SampleBuffer::default(),
+        }
+    }
+
+    #[test]
+    fn other_device_arriving_keeps_connection() {
+        let mut worker = connected();
+        assert!(worker.hotplug(HotplugEvent::Arrived { bus: 1, address: 3 }).is_none());
+        assert!(matches!(worker.device, InternalState::Idle(_)));
+        assert!(matches!(worker.connection, Some(Connection::Dummy(_))));
+    }
+
+    #[test]
+    fn other_device_leaving_keeps_connection() {
+        let mut worker = connected();
+        worker.hotplug(HotplugEvent::Left { bus: 1, address: 3 });
+        assert!(matches!(worker.device, InternalState::Idle(_)));
+        assert!(worker.port.is_some());
+    }
+
+    #[test]
+    fn reconnects_after_unplug() {
+        let mut worker = connected();
+        worker.hotplug(HotplugEvent::Left { bus: 1, address: 2 });
+        assert!(matches!(worker.device, InternalState::Disconnected));
+        assert!(worker.connection.is_some());
+
+        assert!(worker.hotplug(HotplugEvent::Arrived { bus: 1, address: 2 }).is_some());
+        assert!(matches!(worker.device, InternalState::Idle(_)));
+        assert!(worker.connection.is_some());
+    }
+}
@@ -1,4 +1,6 @@
 use std::any::Any;
+use std::cmp::Ordering;
+use std::path::PathBuf;
 
 use gtk4::glib::SignalHandlerId;
 use gtk4::glib::value::FromValue;
@@ -14,7 +16,7 @@ pub trait BindingLabelColumn: 'static{
     type Item: Any;
     /// Value of the column
     type Value: Binding<Target=Self::Target>;
-    type Target: for<'a> FromValue<'a>;
+    type Target: for<'a> FromValue<'a> + PartialOrd;
 
     /// Name of the column
     const COLUMN_NAME: &'static str;
@@ -47,6 +49,7 @@ impl<C: BindingLabelColumn> RelmColumn for BindingLabelColumnWrapper<C> {
 
     fn bind(item: &mut Self::Item, handler_id: &mut Self::Widgets, root: &mut Self::Root) {
         let value = C::get_cell_value(item);
+        root.set_label(&C::format_cell_value(&value.get()));
         let root = root.clone();
         let sighandler = value.connect_notify_local(Some("value"), move |v, _| {
             let value: <C::Value as Binding>::Target = v.get();
@@ -63,6 +66,51 @@ impl<C: BindingLabelColumn> RelmColumn for BindingLabelColumnWrapper<C> {
     }
 
     fn sort_fn() -> OrdFn<Self::Item> {
-        None
+        if !C::ENABLE_SORT {
+            return None;
+        }
+        Some(Box::new(|a, b| {
+            let a = C::get_cell_value(a).get();
+            let b = C::get_cell_value(b).get();
+            a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+        }))
+    }
+}
+
+/// Show a file chooser transient for `window` and call `on_chosen` with the picked path.
+pub fn choose_file(window: &gtk::Window,
+                   title: &str,
+                   action: gtk::FileChooserAction,
+                   filter: (&str, &str),
+                   on_chosen: impl Fn(PathBuf) + 'static) {
+    let accept = match action {
+        gtk::FileChooserAction::Save => "Save",
+        _ => "Open",
+    };
+    let dialog = gtk::FileChooserDialog::new(
+        Some(title),
+        Some(window),
+        action,
+        &[("Cancel", gtk::ResponseType::Cancel), (accept, gtk::ResponseType::Accept)],
+    );
+    dialog.set_modal(true);
+
+    let (name, pattern) = filter;
+    if action == gtk::FileChooserAction::Save {
+        dialog.set_current_name(&format!("untitled{}", pattern.trim_start_matches('*')));
     }
-}
\ No newline at end of file
+    let file_filter = gtk::FileFilter::new();
+    file_filter.set_name(Some(name));
+    file_filter.add_pattern(pattern);
+    dialog.add_filter(&file_filter);
+
+    dialog.connect_response(move |dialog, response| {
+        if response == gtk::ResponseType::Accept {
+            if let Some(path) = dialog.file().and_then(|f| f.path()) {
+                on_chosen(path);
+            }
+        }
+        dialog.destroy();
+    });
+    dialog.present();
+}